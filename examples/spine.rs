@@ -1,35 +1,29 @@
-use std::fs::File;
-
 use bevy::prelude::*;
-use bevy_spine::{
-    spine::Atlas,
-    sprite::{Rotation, Sprite, SpriteBundle, SpritePlugin, SpriteShape},
-    SpinePlugin,
-};
-
-#[derive(Default)]
-struct AvailableSprites(Vec<Handle<Sprite>>);
+use bevy_spine::{animation::AnimationPlayer, SpinePlugin};
 
 fn main() {
     App::build()
         .add_plugins(DefaultPlugins)
-        .add_plugin(SpritePlugin)
-        .add_plugin(SpinePlugin)
-        .init_resource::<AvailableSprites>()
+        .add_plugins(SpinePlugin::default())
         .add_startup_system(setup.system())
-        //.add_system(update.system())
+        .add_system(play.system())
         .run();
 }
 
 fn setup(
-    commands: &mut Commands,
+    mut commands: Commands,
     asset_server: Res<AssetServer>,
-    mut available_sprites: ResMut<AvailableSprites>,
-    mut sprites: ResMut<Assets<Sprite>>,
+    mut scene_spawner: ResMut<SceneSpawner>,
 ) {
-    commands.spawn(OrthographicCameraBundle::new_2d());
-    // commands.spawn(SpriteBundle {
-    //     sprite: atlas_sprites[1].clone(),
-    //     ..Default::default()
-    // });
+    commands
+        .spawn()
+        .insert_bundle(OrthographicCameraBundle::new_2d());
+
+    scene_spawner.spawn(asset_server.load("hero/hero.spine_json#Scene"));
+}
+
+fn play(mut players: Query<&mut AnimationPlayer, Added<AnimationPlayer>>) {
+    for mut player in players.iter_mut() {
+        player.play("idle", true);
+    }
 }
@@ -30,11 +30,13 @@ fn setup(
     let reader = File::open("assets/hero/hero.atlas").unwrap();
     let atlas = Atlas::parse(reader).unwrap();
 
-    let texture: Handle<Texture> = asset_server.load(format!("hero/{}", atlas.name).as_str());
+    // Hero atlas only have a single page
+    let page = &atlas.pages[0];
+    let texture: Handle<Texture> = asset_server.load(format!("hero/{}", page.name).as_str());
 
     let atlas_sprites = &mut available_sprites.0;
 
-    let mut atlas_size: Vec2 = atlas.size.into();
+    let mut atlas_size: Vec2 = page.size.into();
     atlas_size = atlas_size.recip();
 
     // ! FIXME: 1 pixel line and row is been trimmed
@@ -0,0 +1,190 @@
+use bevy::prelude::*;
+
+use crate::spine::{
+    spine::{Animation, AnimationEvent},
+    Spine,
+};
+
+/// Fired when an animation playback crosses one of it's keyed events
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpineEvent {
+    /// Skeleton root entity
+    pub entity: Entity,
+    pub name: String,
+    pub int: i64,
+    pub float: f32,
+    pub string: Option<String>,
+    pub audio: Option<String>,
+    pub volume: f32,
+    pub balance: f32,
+}
+
+impl SpineEvent {
+    /// Event keyed in an animation, values not given by the key are taken from [`Spine::events`]
+    pub fn new(entity: Entity, spine: &Spine, event: &AnimationEvent) -> Self {
+        let name = event.name.clone().unwrap_or_default();
+        let data = spine.events.get(&name);
+        Self {
+            entity,
+            int: event
+                .int
+                .map(|int| int as i64)
+                .or_else(|| data.map(|data| data.int))
+                .unwrap_or(0),
+            float: event
+                .float
+                .or_else(|| data.map(|data| data.float))
+                .unwrap_or(0.0),
+            string: event
+                .string
+                .clone()
+                .or_else(|| data.and_then(|data| data.string.clone())),
+            audio: event
+                .audio
+                .clone()
+                .or_else(|| data.and_then(|data| data.audio.clone())),
+            volume: event
+                .volume
+                .or_else(|| data.map(|data| data.volume))
+                .unwrap_or(1.0),
+            balance: event
+                .balance
+                .or_else(|| data.map(|data| data.balance))
+                .unwrap_or(0.0),
+            name,
+        }
+    }
+}
+
+/// Events keyed after `last` and up to `time`, both are the playback time (not wrapped by the loop);
+///
+/// A negative `last` means the playback just started, so events keyed at 0 are included
+pub fn animation_events(
+    animation: &Animation,
+    last: f32,
+    time: f32,
+    looping: bool,
+) -> Vec<&AnimationEvent> {
+    let mut events = vec![];
+    if time <= last || animation.events.is_empty() {
+        return events;
+    }
+
+    let duration = animation.duration();
+    if !looping || duration <= 0.0 {
+        events.extend(
+            animation
+                .events
+                .iter()
+                .filter(|event| event.time > last && event.time <= time),
+        );
+        return events;
+    }
+
+    // Visit every loop iteration crossed
+    let first = (last.max(0.0) / duration).floor();
+    let mut iteration = first;
+    while iteration * duration <= time {
+        let start = iteration * duration;
+        let from = if iteration == first {
+            last - start
+        } else {
+            -1.0
+        };
+        let to = (time - start).min(duration);
+        events.extend(
+            animation
+                .events
+                .iter()
+                .filter(|event| event.time > from && event.time <= to),
+        );
+        iteration += 1.0;
+    }
+
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(name: &str, time: f32) -> AnimationEvent {
+        AnimationEvent {
+            name: Some(name.to_string()),
+            time,
+            ..Default::default()
+        }
+    }
+
+    fn names(events: Vec<&AnimationEvent>) -> Vec<&str> {
+        events
+            .iter()
+            .map(|event| event.name.as_deref().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn events_crossed_by_playback() {
+        let animation = Animation {
+            events: vec![event("start", 0.0), event("middle", 0.5), event("end", 1.0)],
+            ..Default::default()
+        };
+
+        // Events at the start only fire when the playback starts
+        assert_eq!(
+            names(animation_events(&animation, -1.0, 0.1, false)),
+            ["start"]
+        );
+        assert!(animation_events(&animation, 0.0, 0.1, false).is_empty());
+        assert_eq!(
+            names(animation_events(&animation, 0.1, 2.0, false)),
+            ["middle", "end"]
+        );
+
+        // Wraps around the loop
+        assert_eq!(
+            names(animation_events(&animation, 0.75, 1.25, true)),
+            ["end", "start"]
+        );
+        assert_eq!(
+            names(animation_events(&animation, 0.25, 2.75, true)),
+            ["middle", "end", "start", "middle", "end", "start", "middle"]
+        );
+
+        // Paused or going backwards
+        assert!(animation_events(&animation, 0.5, 0.5, true).is_empty());
+        assert!(animation_events(&animation, 0.75, 0.25, true).is_empty());
+    }
+
+    #[test]
+    fn event_values_default_to_the_event_data() {
+        let json = r#"{"skeleton":{"spine":"3.8","width":0,"height":0,"images":"","audio":""},
+        "bones":[{"name":"root"}],"slots":[],"skins":[],
+        "events":{"hit":{"int":3,"float":0.5,"string":"ouch","audio":"hit.ogg","volume":0.75,"balance":-0.5}},
+        "animations":{"attack":{"events":[{"time":0,"name":"hit"},{"time":1,"name":"hit","int":0,"float":2,"volume":1}]}}}"#;
+        let spine = Spine::parse(json.as_bytes()).unwrap();
+        let events = &spine.animations["attack"].events;
+        let entity = Entity::new(0);
+
+        assert_eq!(
+            SpineEvent::new(entity, &spine, &events[0]),
+            SpineEvent {
+                entity,
+                name: "hit".to_string(),
+                int: 3,
+                float: 0.5,
+                string: Some("ouch".to_string()),
+                audio: Some("hit.ogg".to_string()),
+                volume: 0.75,
+                balance: -0.5,
+            }
+        );
+
+        // Keyed values override the event data, even when zero
+        let event = SpineEvent::new(entity, &spine, &events[1]);
+        assert_eq!(event.int, 0);
+        assert_eq!(event.float, 2.0);
+        assert_eq!(event.volume, 1.0);
+        assert_eq!(event.balance, -0.5);
+    }
+}
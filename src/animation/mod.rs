@@ -0,0 +1,46 @@
+use bevy::prelude::*;
+
+mod events;
+mod player;
+pub mod sampling;
+mod state;
+pub mod timelines;
+
+pub use events::*;
+pub use player::*;
+pub use state::*;
+
+use crate::transform::Transform2D5System;
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+pub enum AnimationSystem {
+    Player,
+    State,
+}
+
+/// Adds the systems needed to animate the [`Skeleton`](crate::Skeleton) bones and the [`SpineEvent`]s
+/// fired by them, they run in the [`CoreStage::PostUpdate`] just before the transform propagation
+#[derive(Default)]
+pub struct SpineAnimationPlugin;
+
+impl Plugin for SpineAnimationPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<SpineEvent>()
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                animation_player_system
+                    .system()
+                    .label(AnimationSystem::Player)
+                    .before(Transform2D5System::PropagateTransform)
+                    .before(Transform2D5System::PropagateTransform2D),
+            )
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                animation_state_system
+                    .system()
+                    .label(AnimationSystem::State)
+                    .before(Transform2D5System::PropagateTransform)
+                    .before(Transform2D5System::PropagateTransform2D),
+            );
+    }
+}
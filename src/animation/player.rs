@@ -0,0 +1,114 @@
+use bevy::prelude::*;
+
+use super::{
+    events::{animation_events, SpineEvent},
+    timelines::{animation_time, Pose, PoseTargets},
+};
+use crate::{skeleton::Skeleton, spine::Spine};
+
+/// Plays a single animation of the [`Skeleton`], found in the root entity of the skeleton scene;
+///
+/// While playing the skeleton is posed every frame starting from the setup pose
+#[derive(Debug, Clone, Reflect)]
+#[reflect(Component)]
+pub struct AnimationPlayer {
+    animation: Option<String>,
+    pub looping: bool,
+    /// Playback speed multiplier
+    pub speed: f32,
+    /// Time elapsed since the animation started playing, in seconds
+    pub time: f32,
+    pub paused: bool,
+    /// Bones must be reset to the setup pose
+    #[reflect(ignore)]
+    reset: bool,
+}
+
+impl Default for AnimationPlayer {
+    fn default() -> Self {
+        Self {
+            animation: None,
+            looping: false,
+            speed: 1.0,
+            time: 0.0,
+            paused: false,
+            reset: false,
+        }
+    }
+}
+
+impl AnimationPlayer {
+    /// Plays the animation from the start
+    pub fn play(&mut self, name: &str, looping: bool) -> &mut Self {
+        self.animation = Some(name.to_string());
+        self.looping = looping;
+        self.time = 0.0;
+        self.paused = false;
+        self.reset = true;
+        self
+    }
+
+    /// Stops the current animation and resets the skeleton to the setup pose
+    pub fn stop(&mut self) -> &mut Self {
+        self.animation = None;
+        self.time = 0.0;
+        self.reset = true;
+        self
+    }
+
+    /// Name of the current animation
+    pub fn animation(&self) -> Option<&str> {
+        self.animation.as_deref()
+    }
+}
+
+pub fn animation_player_system(
+    time: Res<Time>,
+    spines: Res<Assets<Spine>>,
+    mut players: Query<(Entity, &mut AnimationPlayer, &Skeleton)>,
+    mut targets: PoseTargets,
+    mut pose: Local<Pose>,
+    mut spine_events: EventWriter<SpineEvent>,
+) {
+    let delta = time.delta_seconds();
+    for (entity, mut player, skeleton) in players.iter_mut() {
+        let spine = if let Some(spine) = spines.get(&skeleton.spine) {
+            spine
+        } else {
+            continue;
+        };
+
+        // Events keyed at the start only fire when the animation starts playing,
+        // time changes made outside the system (seeking) don't fire any events
+        let last = if player.reset { -1.0 } else { player.time };
+
+        if player.reset {
+            player.reset = false;
+            pose.set_to_setup(spine);
+            pose.write(skeleton, &mut targets);
+        }
+
+        let animation = if let Some(animation) = player
+            .animation
+            .as_ref()
+            .and_then(|name| spine.animations.get(name))
+        {
+            animation
+        } else {
+            continue;
+        };
+
+        if !player.paused {
+            player.time += delta * player.speed;
+        }
+
+        for event in animation_events(animation, last, player.time, player.looping) {
+            spine_events.send(SpineEvent::new(entity, spine, event));
+        }
+
+        let time = animation_time(player.time, animation.duration(), player.looping);
+        pose.set_to_setup(spine);
+        pose.apply(spine, animation, time, 1.0);
+        pose.write(skeleton, &mut targets);
+    }
+}
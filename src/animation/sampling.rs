@@ -0,0 +1,176 @@
+use crate::spine::spine::{
+    AnimationDeform, AnimationIk, AnimationTransform, ColorKeyframe, Interpolation,
+    PathMixKeyframe, PathPositionKeyframe, PathSpacingKeyframe, RotateKeyframe, ScaleKeyframe,
+    ShearKeyframe, TranslateKeyframe, TwoColorKeyframe,
+};
+
+/// Bezier curves are evaluated by bisection, this is the number of iterations used
+const BEZIER_ITERATIONS: usize = 20;
+
+/// Common keyframe properties
+pub trait Keyframe {
+    fn time(&self) -> f32;
+
+    /// Interpolation used from this keyframe to the next one
+    fn curve(&self) -> &Interpolation;
+}
+
+macro_rules! impl_keyframe {
+    ($($t:ty),*) => {
+        $(
+            impl Keyframe for $t {
+                #[inline]
+                fn time(&self) -> f32 {
+                    self.time
+                }
+
+                #[inline]
+                fn curve(&self) -> &Interpolation {
+                    &self.curve
+                }
+            }
+        )*
+    };
+}
+
+impl_keyframe!(
+    RotateKeyframe,
+    TranslateKeyframe,
+    ScaleKeyframe,
+    ShearKeyframe,
+    AnimationIk,
+    AnimationTransform,
+    PathPositionKeyframe,
+    PathSpacingKeyframe,
+    PathMixKeyframe,
+    AnimationDeform,
+    ColorKeyframe,
+    TwoColorKeyframe
+);
+
+/// Finds the keyframes surrounding `time` and the interpolation factor between them,
+/// returns `None` before the first keyframe
+pub fn sample<K: Keyframe>(keyframes: &[K], time: f32) -> Option<(&K, &K, f32)> {
+    let first = keyframes.first()?;
+    if time < first.time() {
+        return None;
+    }
+
+    match keyframes.iter().position(|k| k.time() > time) {
+        Some(index) => {
+            let prev = &keyframes[index - 1];
+            let next = &keyframes[index];
+            let percent = (time - prev.time()) / (next.time() - prev.time());
+            Some((prev, next, curve_percent(prev.curve(), percent)))
+        }
+        None => {
+            let last = keyframes.last().unwrap();
+            Some((last, last, 0.0))
+        }
+    }
+}
+
+/// Remaps the linear `percent` between two keyframes using the `curve`
+pub fn curve_percent(curve: &Interpolation, percent: f32) -> f32 {
+    match curve {
+        Interpolation::Linear => percent,
+        Interpolation::Stepped => 0.0,
+        Interpolation::BezierCurve([cx1, cy1, cx2, cy2]) => {
+            // Find the curve parameter `t` where `x(t) == percent`
+            let mut low = 0.0;
+            let mut high = 1.0;
+            let mut t = percent;
+            for _ in 0..BEZIER_ITERATIONS {
+                let x = bezier(*cx1, *cx2, t);
+                if (x - percent).abs() < 1e-6 {
+                    break;
+                }
+                if x < percent {
+                    low = t;
+                } else {
+                    high = t;
+                }
+                t = (low + high) * 0.5;
+            }
+            bezier(*cy1, *cy2, t)
+        }
+    }
+}
+
+/// Cubic bezier from 0 to 1 with the control points `c1` and `c2`
+#[inline]
+fn bezier(c1: f32, c2: f32, t: f32) -> f32 {
+    let u = 1.0 - t;
+    3.0 * u * u * t * c1 + 3.0 * u * t * t * c2 + t * t * t
+}
+
+/// Linear interpolation
+#[inline]
+pub fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+/// Wraps an angle in degrees to the `[-180, 180]` range
+#[inline]
+pub fn wrap_degrees(angle: f32) -> f32 {
+    angle - (angle / 360.0).round() * 360.0
+}
+
+/// Interpolates two angles in degrees by the shortest path
+#[inline]
+pub fn lerp_degrees(a: f32, b: f32, t: f32) -> f32 {
+    a + wrap_degrees(b - a) * t
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rotate(time: f32, angle: f32, curve: Interpolation) -> RotateKeyframe {
+        RotateKeyframe { time, angle, curve }
+    }
+
+    #[test]
+    fn sample_keyframes() {
+        let keyframes = vec![
+            rotate(0.5, 0.0, Interpolation::Linear),
+            rotate(1.0, 90.0, Interpolation::Stepped),
+            rotate(2.0, 180.0, Interpolation::Linear),
+        ];
+
+        assert!(sample(&keyframes, 0.0).is_none());
+
+        let (prev, next, percent) = sample(&keyframes, 0.75).unwrap();
+        assert_eq!((prev.angle, next.angle), (0.0, 90.0));
+        assert!((percent - 0.5).abs() < 1e-6);
+
+        let (prev, _, percent) = sample(&keyframes, 1.5).unwrap();
+        assert_eq!(prev.angle, 90.0);
+        assert_eq!(percent, 0.0);
+
+        let (prev, next, _) = sample(&keyframes, 3.0).unwrap();
+        assert_eq!((prev.angle, next.angle), (180.0, 180.0));
+    }
+
+    #[test]
+    fn bezier_curve() {
+        // Control points along the diagonal make a linear curve
+        let linear = Interpolation::BezierCurve([1.0 / 3.0, 1.0 / 3.0, 2.0 / 3.0, 2.0 / 3.0]);
+        for i in 0..=10 {
+            let percent = i as f32 / 10.0;
+            assert!((curve_percent(&linear, percent) - percent).abs() < 1e-4);
+        }
+
+        let ease = Interpolation::BezierCurve([0.25, 0.0, 0.75, 1.0]);
+        assert!(curve_percent(&ease, 0.25) < 0.25);
+        assert!(curve_percent(&ease, 0.75) > 0.75);
+        assert!((curve_percent(&ease, 0.5) - 0.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn shortest_rotation() {
+        assert!((lerp_degrees(170.0, -170.0, 0.5) - 180.0).abs() < 1e-4);
+        assert!((lerp_degrees(-10.0, 10.0, 0.5)).abs() < 1e-4);
+        assert!((wrap_degrees(270.0) + 90.0).abs() < 1e-4);
+    }
+}
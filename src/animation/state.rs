@@ -0,0 +1,425 @@
+use std::collections::VecDeque;
+
+use bevy::{prelude::*, utils::HashMap};
+
+use super::{
+    events::{animation_events, SpineEvent},
+    timelines::{animation_time, Pose, PoseTargets},
+};
+use crate::{
+    skeleton::Skeleton,
+    spine::{
+        spine::{Animation, AnimationEvent},
+        Spine,
+    },
+};
+
+/// Mix (crossfade) durations between animations
+#[derive(Default, Debug, Clone)]
+pub struct AnimationStateData {
+    /// Mix duration used when none was specified for a pair of animations
+    pub default_mix: f32,
+    mixes: HashMap<(String, String), f32>,
+}
+
+impl AnimationStateData {
+    pub fn with_default_mix(default_mix: f32) -> Self {
+        Self {
+            default_mix,
+            mixes: Default::default(),
+        }
+    }
+
+    /// Sets the mix duration when changing from the animation `from` to `to`
+    pub fn set_mix(&mut self, from: &str, to: &str, duration: f32) -> &mut Self {
+        self.mixes
+            .insert((from.to_string(), to.to_string()), duration);
+        self
+    }
+
+    /// Mix duration when changing from the animation `from` to `to`
+    pub fn mix(&self, from: &str, to: &str) -> f32 {
+        // TODO: Avoid allocating the key
+        self.mixes
+            .get(&(from.to_string(), to.to_string()))
+            .copied()
+            .unwrap_or(self.default_mix)
+    }
+}
+
+/// Animation playing (or queued) in a track
+#[derive(Debug, Clone)]
+pub struct TrackEntry {
+    animation: String,
+    pub looping: bool,
+    /// Seconds to wait before playing, when queued it's relative to the previous entry start time
+    pub delay: f32,
+    /// Time elapsed since the entry started playing, in seconds
+    pub track_time: f32,
+    /// Playback speed multiplier
+    pub time_scale: f32,
+    /// How much the entry is mixed with the tracks bellow it
+    pub alpha: f32,
+    /// Crossfade duration from the previous entry
+    pub mix_duration: f32,
+    /// Time elapsed since the crossfade started, it isn't affected by the entry [`time_scale`](Self::time_scale)
+    pub mix_time: f32,
+    /// While mixing out, the entry still fires events until the crossfade progress reaches this threshold
+    pub event_threshold: f32,
+    /// Queued entries with non positive delays are relative to the previous entry end,
+    /// they are resolved once the previous entry starts playing
+    delay_resolved: bool,
+    /// Events keyed at the start are fired once the entry starts playing
+    started: bool,
+    mixing_from: Option<Box<TrackEntry>>,
+}
+
+impl TrackEntry {
+    fn new(animation: &str, looping: bool) -> Self {
+        Self {
+            animation: animation.to_string(),
+            looping,
+            delay: 0.0,
+            track_time: 0.0,
+            time_scale: 1.0,
+            alpha: 1.0,
+            mix_duration: 0.0,
+            mix_time: 0.0,
+            event_threshold: 0.0,
+            delay_resolved: true,
+            started: false,
+            mixing_from: None,
+        }
+    }
+
+    pub fn animation(&self) -> &str {
+        &self.animation
+    }
+
+    /// Entry being faded out
+    pub fn mixing_from(&self) -> Option<&TrackEntry> {
+        self.mixing_from.as_deref()
+    }
+
+    /// Crossfade progress, from 0 to 1
+    pub fn mix(&self) -> f32 {
+        if self.mix_duration > 0.0 {
+            (self.mix_time / self.mix_duration).min(1.0)
+        } else {
+            1.0
+        }
+    }
+
+    /// Advances the entry and the ones been mixed out, the events crossed by them are added to `events`
+    /// when `fire_events` is set
+    fn update<'a>(
+        &mut self,
+        spine: &'a Spine,
+        delta: f32,
+        fire_events: bool,
+        events: &mut Vec<&'a AnimationEvent>,
+    ) {
+        // Time changes made outside the update (seeking) don't fire events
+        let last = if self.started { self.track_time } else { -1.0 };
+        self.started = true;
+        self.track_time += delta * self.time_scale;
+        if fire_events {
+            if let Some(animation) = spine.animations.get(&self.animation) {
+                events.extend(animation_events(
+                    animation,
+                    last,
+                    self.track_time,
+                    self.looping,
+                ));
+            }
+        }
+
+        self.mix_time += delta;
+        let mix = self.mix();
+        if mix >= 1.0 {
+            self.mixing_from = None;
+        } else if let Some(from) = &mut self.mixing_from {
+            let fire_events = mix < from.event_threshold;
+            from.update(spine, delta, fire_events, events);
+        }
+    }
+
+    /// Samples the entry, crossfading with the entries been mixed out
+    fn apply(&self, spine: &Spine, pose: &mut Pose, alpha: f32) {
+        let alpha = alpha * self.alpha;
+        let animation = if let Some(animation) = spine.animations.get(&self.animation) {
+            animation
+        } else {
+            return;
+        };
+
+        if let Some(from) = &self.mixing_from {
+            let mut from_pose = pose.clone();
+            from.apply(spine, &mut from_pose, 1.0);
+
+            let mut to_pose = pose.clone();
+            self.apply_animation(spine, animation, &mut to_pose);
+
+            from_pose.mix(&to_pose, self.mix());
+            pose.mix(&from_pose, alpha);
+        } else {
+            let time = animation_time(self.track_time, animation.duration(), self.looping);
+            pose.apply(spine, animation, time, alpha);
+        }
+    }
+
+    fn apply_animation(&self, spine: &Spine, animation: &Animation, pose: &mut Pose) {
+        let time = animation_time(self.track_time, animation.duration(), self.looping);
+        pose.apply(spine, animation, time, 1.0);
+    }
+}
+
+#[derive(Default, Debug, Clone)]
+struct Track {
+    current: Option<TrackEntry>,
+    queue: VecDeque<TrackEntry>,
+}
+
+/// Plays animations in multiple tracks, each track is layered on top of the previous ones;
+///
+/// Changing the animation of a track crossfades between them by the mix durations found
+/// in the [`AnimationStateData`]; Insert it on the root entity of the skeleton scene
+/// (along side the [`Skeleton`]) in place of the [`AnimationPlayer`](super::AnimationPlayer)
+#[derive(Debug, Clone)]
+pub struct AnimationState {
+    pub data: AnimationStateData,
+    /// Playback speed multiplier of all tracks
+    pub time_scale: f32,
+    tracks: Vec<Track>,
+}
+
+impl Default for AnimationState {
+    fn default() -> Self {
+        Self::new(AnimationStateData::default())
+    }
+}
+
+impl AnimationState {
+    pub fn new(data: AnimationStateData) -> Self {
+        Self {
+            data,
+            time_scale: 1.0,
+            tracks: vec![],
+        }
+    }
+
+    /// Entry currently playing in the track
+    pub fn current(&self, track: usize) -> Option<&TrackEntry> {
+        self.tracks
+            .get(track)
+            .and_then(|track| track.current.as_ref())
+    }
+
+    pub fn current_mut(&mut self, track: usize) -> Option<&mut TrackEntry> {
+        self.tracks
+            .get_mut(track)
+            .and_then(|track| track.current.as_mut())
+    }
+
+    /// Plays the animation right away, discarding any queued animations for the track;
+    /// The previous animation is mixed out
+    pub fn set_animation(&mut self, track: usize, name: &str, looping: bool) -> &mut TrackEntry {
+        let mut entry = TrackEntry::new(name, looping);
+        let track = track_mut(&mut self.tracks, track);
+        track.queue.clear();
+        if let Some(from) = track.current.take() {
+            entry.mix_duration = self.data.mix(&from.animation, name);
+            entry.mixing_from = Some(Box::new(from));
+        }
+        track.current.get_or_insert(entry)
+    }
+
+    /// Queues the animation to be played after the previous entry of the track, if `delay`
+    /// is less or equal to 0 the animation will start playing when the previous one ends minus the mix duration
+    /// (plus the `delay`), otherwise it's relative to the previous entry start
+    pub fn add_animation(
+        &mut self,
+        track: usize,
+        name: &str,
+        looping: bool,
+        delay: f32,
+    ) -> &mut TrackEntry {
+        let mut entry = TrackEntry::new(name, looping);
+        entry.delay = delay;
+
+        let track = track_mut(&mut self.tracks, track);
+        let last = track.queue.back().or_else(|| track.current.as_ref());
+        if let Some(last) = last {
+            entry.mix_duration = self.data.mix(&last.animation, name);
+            entry.delay_resolved = delay > 0.0;
+            track.queue.push_back(entry);
+            track.queue.back_mut().unwrap()
+        } else {
+            entry.delay = delay.max(0.0);
+            track.current.get_or_insert(entry)
+        }
+    }
+
+    /// Stops the track animations, the bones keyed by them will return to the setup pose
+    pub fn clear_track(&mut self, track: usize) {
+        if let Some(track) = self.tracks.get_mut(track) {
+            track.current = None;
+            track.queue.clear();
+        }
+    }
+
+    pub fn clear_tracks(&mut self) {
+        self.tracks.clear();
+    }
+
+    /// Advances the tracks time and plays the queued entries,
+    /// the events crossed by the current entries are added to `events`
+    fn update<'a>(&mut self, spine: &'a Spine, delta: f32, events: &mut Vec<&'a AnimationEvent>) {
+        let delta = delta * self.time_scale;
+        for track in &mut self.tracks {
+            let current = if let Some(current) = &mut track.current {
+                current
+            } else {
+                continue;
+            };
+
+            // Waiting to start
+            let mut delta = delta;
+            if current.delay > 0.0 {
+                current.delay -= delta;
+                if current.delay > 0.0 {
+                    continue;
+                }
+                delta = -current.delay;
+                current.delay = 0.0;
+            }
+
+            current.update(spine, delta, true, events);
+
+            if let Some(next) = track.queue.front_mut() {
+                if !next.delay_resolved {
+                    next.delay_resolved = true;
+                    next.delay += end_delay(spine, current) - next.mix_duration;
+                }
+
+                if current.track_time >= next.delay {
+                    let mut next = track.queue.pop_front().unwrap();
+                    next.mix_time = current.track_time - next.delay;
+                    next.track_time = next.mix_time * next.time_scale;
+                    next.delay = 0.0;
+                    next.mixing_from = track.current.take().map(Box::new);
+                    if next.mix() >= 1.0 {
+                        next.mixing_from = None;
+                    }
+                    track.current = Some(next);
+                }
+            }
+        }
+    }
+
+    /// Samples every track on top of the setup pose
+    fn apply(&self, spine: &Spine, pose: &mut Pose) {
+        pose.set_to_setup(spine);
+        for track in &self.tracks {
+            if let Some(current) = &track.current {
+                if current.delay <= 0.0 {
+                    current.apply(spine, pose, 1.0);
+                }
+            }
+        }
+    }
+}
+
+fn track_mut(tracks: &mut Vec<Track>, index: usize) -> &mut Track {
+    if tracks.len() <= index {
+        tracks.resize_with(index + 1, Default::default);
+    }
+    &mut tracks[index]
+}
+
+/// Time when the `entry` ends (or the current loop iteration ends)
+fn end_delay(spine: &Spine, entry: &TrackEntry) -> f32 {
+    let duration = spine
+        .animations
+        .get(&entry.animation)
+        .map_or(0.0, Animation::duration);
+
+    if duration > 0.0 {
+        if entry.looping {
+            duration * (1.0 + (entry.track_time / duration).floor())
+        } else {
+            duration.max(entry.track_time)
+        }
+    } else {
+        entry.track_time
+    }
+}
+
+pub fn animation_state_system(
+    time: Res<Time>,
+    spines: Res<Assets<Spine>>,
+    mut states: Query<(Entity, &mut AnimationState, &Skeleton)>,
+    mut targets: PoseTargets,
+    mut pose: Local<Pose>,
+    mut spine_events: EventWriter<SpineEvent>,
+) {
+    let delta = time.delta_seconds();
+    for (entity, mut state, skeleton) in states.iter_mut() {
+        let spine = if let Some(spine) = spines.get(&skeleton.spine) {
+            spine
+        } else {
+            continue;
+        };
+
+        let mut events = vec![];
+        state.update(spine, delta, &mut events);
+        for event in events {
+            spine_events.send(SpineEvent::new(entity, spine, event));
+        }
+
+        state.apply(spine, &mut pose);
+        pose.write(skeleton, &mut targets);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn update(state: &mut AnimationState, spine: &Spine, delta: f32) -> Vec<String> {
+        let mut events = vec![];
+        state.update(spine, delta, &mut events);
+        events
+            .iter()
+            .map(|event| event.name.clone().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn mixing_out_entry_fires_events_until_threshold() {
+        let json = r#"{"skeleton":{"spine":"3.8","width":0,"height":0,"images":"","audio":""},
+        "bones":[{"name":"root"}],"slots":[],"skins":[],"events":{"step":{},"jump":{}},
+        "animations":{
+            "walk":{"events":[{"time":0.25,"name":"step"},{"time":0.75,"name":"step"}]},
+            "jump":{"events":[{"time":0,"name":"jump"}]}
+        }}"#;
+        let spine = Spine::parse(json.as_bytes()).unwrap();
+
+        let mut state = AnimationState::new(AnimationStateData::with_default_mix(1.0));
+        state.set_animation(0, "walk", false).event_threshold = 0.5;
+        assert!(update(&mut state, &spine, 0.1).is_empty());
+
+        state.set_animation(0, "jump", false).time_scale = 2.0;
+        assert_eq!(update(&mut state, &spine, 0.2), ["jump", "step"]);
+
+        // The mix isn't scaled by the entry speed
+        let current = state.current(0).unwrap();
+        assert_eq!(current.track_time, 0.4);
+        assert_eq!(current.mix_time, 0.2);
+        assert_eq!(current.mixing_from().unwrap().track_time, 0.3);
+
+        // Past the threshold
+        assert!(update(&mut state, &spine, 0.5).is_empty());
+        assert!(state.current(0).unwrap().mixing_from().is_some());
+    }
+}
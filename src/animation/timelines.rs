@@ -0,0 +1,846 @@
+use bevy::{ecs::system::SystemParam, prelude::*};
+
+use super::sampling::{lerp, lerp_degrees, sample};
+use crate::{
+    attachment::{AttachmentVertices, MeshAttachment},
+    constraints::{
+        CopyPositionConstraint, CopyRotationConstraint, CopyScaleConstraint, CopyShearConstraint,
+        FollowPathConstraint, IkConstraint,
+    },
+    skeleton::{bone_setup_transform, bone_shear, Skeleton},
+    slot::{DrawOrder, Slot},
+    spine::{
+        spine::{
+            find_target, Animation, AnimationBone, AnimationDeform, AnimationDrawOrder,
+            AnimationIk, AnimationPath, AnimationSlot, AnimationTransform, Bone, IkConstraints,
+            PathConstraints, SequenceKeyframe, SequenceMode, SkinAttachment, TransformConstraints,
+        },
+        Spine,
+    },
+    sprite::SpriteInstance,
+    transform::Transform2D,
+};
+
+/// Animated properties of an [`IkConstraint`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IkPose {
+    pub mix: f32,
+    pub softness: f32,
+    pub bend_positive: bool,
+    pub compress: bool,
+    pub stretch: bool,
+}
+
+impl IkPose {
+    fn setup(ik: &IkConstraints) -> Self {
+        Self {
+            mix: ik.mix,
+            softness: ik.softness,
+            bend_positive: ik.bend_positive,
+            compress: ik.compress,
+            stretch: ik.stretch,
+        }
+    }
+
+    /// Interpolates towards `other`, the flags are taken from `other`
+    fn lerp(&self, other: &IkPose, t: f32) -> Self {
+        if t <= 0.0 {
+            return *self;
+        }
+
+        Self {
+            mix: lerp(self.mix, other.mix, t),
+            softness: lerp(self.softness, other.softness, t),
+            ..*other
+        }
+    }
+}
+
+/// Animated mixes of a [`TransformConstraint`](crate::constraints::TransformConstraint)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TransformConstraintPose {
+    pub rotate_mix: f32,
+    pub translate_mix: f32,
+    pub scale_mix: f32,
+    pub shear_mix: f32,
+}
+
+impl TransformConstraintPose {
+    fn setup(transform: &TransformConstraints) -> Self {
+        Self {
+            rotate_mix: transform.rotate_mix,
+            translate_mix: transform.translate_mix,
+            scale_mix: transform.scale_mix,
+            shear_mix: transform.shear_mix,
+        }
+    }
+
+    fn lerp(&self, other: &TransformConstraintPose, t: f32) -> Self {
+        Self {
+            rotate_mix: lerp(self.rotate_mix, other.rotate_mix, t),
+            translate_mix: lerp(self.translate_mix, other.translate_mix, t),
+            scale_mix: lerp(self.scale_mix, other.scale_mix, t),
+            shear_mix: lerp(self.shear_mix, other.shear_mix, t),
+        }
+    }
+}
+
+/// Animated properties of a [`FollowPathConstraint`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PathConstraintPose {
+    pub position: f32,
+    pub spacing: f32,
+    pub rotate_mix: f32,
+    pub translate_mix: f32,
+}
+
+impl PathConstraintPose {
+    fn setup(path: &PathConstraints) -> Self {
+        Self {
+            position: path.position,
+            spacing: path.spacing,
+            rotate_mix: path.rotate_mix,
+            translate_mix: path.translate_mix,
+        }
+    }
+
+    fn lerp(&self, other: &PathConstraintPose, t: f32) -> Self {
+        Self {
+            position: lerp(self.position, other.position, t),
+            spacing: lerp(self.spacing, other.spacing, t),
+            rotate_mix: lerp(self.rotate_mix, other.rotate_mix, t),
+            translate_mix: lerp(self.translate_mix, other.translate_mix, t),
+        }
+    }
+}
+
+/// Animated properties of a [`Slot`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct SlotPose {
+    pub attachment: Option<String>,
+    /// Frame of the attachment sequence, the setup frame when `None`
+    pub sequence_index: Option<usize>,
+    pub color: Color,
+    /// Tint black color
+    pub dark: Color,
+}
+
+impl SlotPose {
+    fn setup(slot: &crate::spine::spine::Slot) -> Self {
+        Self {
+            attachment: slot.attachment.clone(),
+            sequence_index: None,
+            color: Color::hex(&slot.color).unwrap_or(Color::WHITE),
+            dark: slot
+                .dark
+                .as_ref()
+                .and_then(|dark| Color::hex(dark).ok())
+                .unwrap_or(Color::BLACK),
+        }
+    }
+
+    /// Interpolates towards `other`, the attachment and sequence frame are taken from `other`
+    fn lerp(&self, other: &SlotPose, t: f32) -> Self {
+        if t <= 0.0 {
+            return self.clone();
+        }
+
+        Self {
+            attachment: other.attachment.clone(),
+            sequence_index: other.sequence_index,
+            color: lerp_color(self.color, other.color, t),
+            dark: lerp_color(self.dark, other.dark, t),
+        }
+    }
+}
+
+/// Vertices offsets of a deformed attachment
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeformPose {
+    pub skin: String,
+    /// Slot index in [`Spine::slots`]
+    pub slot: usize,
+    pub attachment: String,
+    /// Offsets added to each position of the attachment vertices
+    pub offsets: Vec<Vec2>,
+}
+
+impl DeformPose {
+    fn matches(&self, skin: &str, slot: usize, attachment: &str) -> bool {
+        self.slot == slot && self.skin == skin && self.attachment == attachment
+    }
+}
+
+/// Skeleton entities components written by a [`Pose`]
+#[derive(SystemParam)]
+pub struct PoseTargets<'a> {
+    pub bones: Query<'a, &'static mut Transform2D>,
+    pub ik_constraints: Query<'a, &'static mut IkConstraint>,
+    pub transform_constraints: Query<
+        'a,
+        (
+            &'static mut CopyRotationConstraint,
+            &'static mut CopyPositionConstraint,
+            &'static mut CopyScaleConstraint,
+            &'static mut CopyShearConstraint,
+        ),
+    >,
+    pub path_constraints: Query<'a, &'static mut FollowPathConstraint>,
+    pub meshes: Query<'a, &'static mut MeshAttachment>,
+    pub slots: Query<'a, (&'static mut Slot, &'static mut SpriteInstance)>,
+    pub draw_order: Query<'a, &'static mut DrawOrder>,
+}
+
+/// Animated properties of a skeleton, animations are sampled and mixed in a pose
+/// before been written to the skeleton entities
+#[derive(Default, Debug, Clone)]
+pub struct Pose {
+    /// Bones transforms in the same order of [`Spine::bones`]
+    pub bones: Vec<Transform2D>,
+    /// Ik constraints in the same order of [`Spine::ik`]
+    pub ik: Vec<IkPose>,
+    /// Transform constraints in the same order of [`Spine::transform`]
+    pub transform: Vec<TransformConstraintPose>,
+    /// Path constraints in the same order of [`Spine::path`]
+    pub path: Vec<PathConstraintPose>,
+    /// Slots in the same order of [`Spine::slots`]
+    pub slots: Vec<SlotPose>,
+    /// Slots indexes in the order they are drawn
+    pub draw_order: Vec<usize>,
+    /// Deformed attachments, any attachment not found here is in the setup pose
+    pub deform: Vec<DeformPose>,
+}
+
+impl Pose {
+    /// Setup pose of the skeleton
+    pub fn setup(spine: &Spine) -> Self {
+        let mut pose = Pose::default();
+        pose.set_to_setup(spine);
+        pose
+    }
+
+    pub fn set_to_setup(&mut self, spine: &Spine) {
+        self.bones.clear();
+        self.bones
+            .extend(spine.bones.iter().map(|bone| bone_setup_transform(bone)));
+        self.ik.clear();
+        self.ik.extend(spine.ik.iter().map(IkPose::setup));
+        self.transform.clear();
+        self.transform
+            .extend(spine.transform.iter().map(TransformConstraintPose::setup));
+        self.path.clear();
+        self.path
+            .extend(spine.path.iter().map(PathConstraintPose::setup));
+        self.slots.clear();
+        self.slots.extend(spine.slots.iter().map(SlotPose::setup));
+        self.draw_order.clear();
+        self.draw_order.extend(0..spine.slots.len());
+        self.deform.clear();
+    }
+
+    /// Writes the pose back to the skeleton entities
+    pub fn write(&self, skeleton: &Skeleton, targets: &mut PoseTargets) {
+        for (transform, entity) in self.bones.iter().zip(skeleton.bones.iter()) {
+            if let Ok(mut bone) = targets.bones.get_mut(*entity) {
+                *bone = *transform;
+            }
+        }
+
+        for (pose, entity) in self.ik.iter().zip(skeleton.ik_constraints.iter()) {
+            if let Ok(mut ik) = targets.ik_constraints.get_mut(*entity) {
+                ik.mix = pose.mix;
+                ik.softness = pose.softness;
+                ik.bend_positive = pose.bend_positive;
+                ik.compress = pose.compress;
+                ik.stretch = pose.stretch;
+            }
+        }
+
+        for (pose, entity) in self
+            .transform
+            .iter()
+            .zip(skeleton.transform_constraints.iter())
+        {
+            if let Ok((mut rotation, mut position, mut scale, mut shear)) =
+                targets.transform_constraints.get_mut(*entity)
+            {
+                rotation.mix = pose.rotate_mix;
+                position.mix = pose.translate_mix;
+                scale.mix = pose.scale_mix;
+                shear.mix = pose.shear_mix;
+            }
+        }
+
+        for (pose, entity) in self.path.iter().zip(skeleton.path_constraints.iter()) {
+            if let Ok(mut path) = targets.path_constraints.get_mut(*entity) {
+                path.position = pose.position;
+                path.spacing = pose.spacing;
+                path.rotate_mix = pose.rotate_mix;
+                path.translate_mix = pose.translate_mix;
+            }
+        }
+
+        for (pose, entity) in self.slots.iter().zip(skeleton.slots.iter()) {
+            if let Ok((mut slot, mut instance)) = targets.slots.get_mut(*entity) {
+                // Only changed attachments must be looked up in the skins
+                if slot.attachment != pose.attachment || slot.sequence_index != pose.sequence_index
+                {
+                    slot.attachment = pose.attachment.clone();
+                    slot.sequence_index = pose.sequence_index;
+                }
+                if instance.color != pose.color || instance.dark != pose.dark {
+                    instance.color = pose.color;
+                    instance.dark = pose.dark;
+                }
+            }
+        }
+
+        for (index, slot) in self.draw_order.iter().enumerate() {
+            if let Some(entity) = skeleton.slots.get(*slot) {
+                if let Ok(mut draw_order) = targets.draw_order.get_mut(*entity) {
+                    if draw_order.0 != index {
+                        draw_order.0 = index;
+                    }
+                }
+            }
+        }
+
+        for (index, entity) in skeleton.slots.iter().enumerate() {
+            if let Ok(mut mesh) = targets.meshes.get_mut(*entity) {
+                let deform = self
+                    .deform
+                    .iter()
+                    .find(|deform| deform.matches(&mesh.skin, index, &mesh.name));
+
+                if let Some(deform) = deform {
+                    mesh.deform.clear();
+                    mesh.deform.extend_from_slice(&deform.offsets);
+                } else if !mesh.deform.is_empty() {
+                    mesh.deform.clear();
+                }
+            }
+        }
+    }
+
+    /// Mixes this pose towards `other` by `alpha`
+    pub fn mix(&mut self, other: &Pose, alpha: f32) {
+        for (a, b) in self.bones.iter_mut().zip(other.bones.iter()) {
+            *a = lerp_transform(a, b, alpha);
+        }
+
+        for (a, b) in self.ik.iter_mut().zip(other.ik.iter()) {
+            *a = a.lerp(b, alpha);
+        }
+
+        for (a, b) in self.transform.iter_mut().zip(other.transform.iter()) {
+            *a = a.lerp(b, alpha);
+        }
+
+        for (a, b) in self.path.iter_mut().zip(other.path.iter()) {
+            *a = a.lerp(b, alpha);
+        }
+
+        for (a, b) in self.slots.iter_mut().zip(other.slots.iter()) {
+            *a = a.lerp(b, alpha);
+        }
+
+        // Draw order is stepped, like the attachments
+        if alpha > 0.0 && self.draw_order != other.draw_order {
+            self.draw_order.clear();
+            self.draw_order.extend_from_slice(&other.draw_order);
+        }
+
+        // Attachments missing in either pose are in the setup pose, without any offsets
+        for a in self.deform.iter_mut() {
+            let b = other
+                .deform
+                .iter()
+                .find(|b| b.matches(&a.skin, a.slot, &a.attachment));
+            lerp_offsets(
+                &mut a.offsets,
+                b.map_or(&[][..], |b| b.offsets.as_slice()),
+                alpha,
+            );
+        }
+
+        for b in &other.deform {
+            if !self
+                .deform
+                .iter()
+                .any(|a| a.matches(&b.skin, b.slot, &b.attachment))
+            {
+                let mut a = DeformPose {
+                    offsets: vec![],
+                    ..b.clone()
+                };
+                lerp_offsets(&mut a.offsets, &b.offsets, alpha);
+                self.deform.push(a);
+            }
+        }
+    }
+
+    /// Applies the `animation` at the given `time`, keyed values are mixed with the current pose by `alpha`;
+    ///
+    /// Timelines targets are found by the indices resolved by [`Spine::resolve_timelines`]
+    pub fn apply(&mut self, spine: &Spine, animation: &Animation, time: f32, alpha: f32) {
+        for (bone_name, timeline) in &animation.bones {
+            if let Some(index) = timeline.target_in(bone_name, &spine.bones, |bone| &bone.name) {
+                if let Some(transform) = self.bones.get_mut(index) {
+                    apply_bone_timeline(&spine.bones[index], timeline, time, alpha, transform);
+                }
+            }
+        }
+
+        for (ik_name, keyframes) in &animation.ik {
+            if let Some(index) = keyframes.target_in(ik_name, &spine.ik, |ik| &ik.name) {
+                if let Some(pose) = self.ik.get_mut(index) {
+                    apply_ik_timeline(keyframes, time, alpha, pose);
+                }
+            }
+        }
+
+        for (transform_name, keyframes) in &animation.transform {
+            if let Some(index) = keyframes.target_in(transform_name, &spine.transform, |t| &t.name)
+            {
+                if let Some(pose) = self.transform.get_mut(index) {
+                    apply_transform_constraint_timeline(keyframes, time, alpha, pose);
+                }
+            }
+        }
+
+        for (path_name, timeline) in &animation.path {
+            if let Some(index) = timeline.target_in(path_name, &spine.path, |path| &path.name) {
+                if let Some(pose) = self.path.get_mut(index) {
+                    apply_path_constraint_timeline(timeline, time, alpha, pose);
+                }
+            }
+        }
+
+        for (slot_name, timeline) in &animation.slots {
+            if let Some(index) = timeline.target_in(slot_name, &spine.slots, |slot| &slot.name) {
+                if let Some(pose) = self.slots.get_mut(index) {
+                    apply_slot_timeline(timeline, time, alpha, pose);
+                }
+            }
+        }
+
+        apply_draw_order_timeline(
+            spine,
+            &animation.draw_order,
+            time,
+            alpha,
+            &mut self.draw_order,
+        );
+
+        for (skin_name, slots) in &animation.attachments {
+            let skin =
+                if let Some(skin) = slots.target_in(skin_name, &spine.skins, |skin| &skin.name) {
+                    &spine.skins[skin]
+                } else {
+                    continue;
+                };
+
+            for (slot_name, attachments) in slots.iter() {
+                let slot = if let Some(slot) =
+                    attachments.target_in(slot_name, &spine.slots, |slot| &slot.name)
+                {
+                    slot
+                } else {
+                    continue;
+                };
+
+                for (attachment_name, timelines) in attachments {
+                    let count = match skin
+                        .attachment(slot_name, attachment_name)
+                        .and_then(SkinAttachment::sequence)
+                    {
+                        Some(sequence) => sequence.count,
+                        None => continue,
+                    };
+
+                    // Only applied while the slot shows the attachment
+                    if let Some(pose) = self.slots.get_mut(slot) {
+                        if pose.attachment.as_ref() == Some(attachment_name) {
+                            apply_sequence_timeline(
+                                &timelines.sequence,
+                                count,
+                                time,
+                                alpha,
+                                &mut pose.sequence_index,
+                            );
+                        }
+                    }
+                }
+            }
+        }
+
+        for (skin_name, slots) in &animation.deform {
+            let skin =
+                if let Some(skin) = slots.target_in(skin_name, &spine.skins, |skin| &skin.name) {
+                    &spine.skins[skin]
+                } else {
+                    continue;
+                };
+
+            for (slot_name, attachments) in slots.iter() {
+                let slot = if let Some(slot) =
+                    attachments.target_in(slot_name, &spine.slots, |slot| &slot.name)
+                {
+                    slot
+                } else {
+                    continue;
+                };
+
+                for (attachment_name, keyframes) in attachments {
+                    let len = match skin
+                        .attachments
+                        .get(slot_name)
+                        .and_then(|attachments| attachments.get(attachment_name))
+                        .and_then(SkinAttachment::mesh)
+                    {
+                        Some(SkinAttachment::Mesh { uvs, vertices, .. }) => {
+                            AttachmentVertices::positions_len(vertices, uvs.len() / 2)
+                        }
+                        _ => continue,
+                    };
+
+                    let index = if let Some(index) = self
+                        .deform
+                        .iter()
+                        .position(|deform| deform.matches(skin_name, slot, attachment_name))
+                    {
+                        index
+                    } else {
+                        self.deform.push(DeformPose {
+                            skin: skin_name.clone(),
+                            slot,
+                            attachment: attachment_name.clone(),
+                            offsets: vec![],
+                        });
+                        self.deform.len() - 1
+                    };
+
+                    apply_deform_timeline(
+                        keyframes,
+                        len,
+                        time,
+                        alpha,
+                        &mut self.deform[index].offsets,
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Keyframes values are relative to the bone setup pose
+pub fn apply_bone_timeline(
+    bone: &Bone,
+    timeline: &AnimationBone,
+    time: f32,
+    alpha: f32,
+    transform: &mut Transform2D,
+) {
+    if let Some((prev, next, percent)) = sample(&timeline.rotate, time) {
+        let target = bone.rotation + lerp_degrees(prev.angle, next.angle, percent);
+        let current = transform.rotation.to_degrees();
+        transform.rotation = lerp_degrees(current, target, alpha).to_radians();
+    }
+
+    if let Some((prev, next, percent)) = sample(&timeline.translate, time) {
+        let target = Vec2::new(
+            bone.x + lerp(prev.x, next.x, percent),
+            bone.y + lerp(prev.y, next.y, percent),
+        );
+        transform.translation = transform.translation.lerp(target, alpha);
+    }
+
+    if let Some((prev, next, percent)) = sample(&timeline.scale, time) {
+        let target = Vec2::new(
+            bone.scale_x * lerp(prev.x, next.x, percent),
+            bone.scale_y * lerp(prev.y, next.y, percent),
+        );
+        transform.scale = transform.scale.lerp(target, alpha);
+    }
+
+    if let Some((prev, next, percent)) = sample(&timeline.shear, time) {
+        let target = bone_shear(
+            bone.shear_x + lerp(prev.x, next.x, percent),
+            bone.shear_y + lerp(prev.y, next.y, percent),
+        );
+        transform.shear = transform.shear.lerp(target, alpha);
+    }
+}
+
+/// Mix and softness are interpolated, the flags are stepped
+pub fn apply_ik_timeline(keyframes: &[AnimationIk], time: f32, alpha: f32, pose: &mut IkPose) {
+    if let Some((prev, next, percent)) = sample(keyframes, time) {
+        let target = IkPose {
+            mix: lerp(prev.mix, next.mix, percent),
+            softness: lerp(prev.softness, next.softness, percent),
+            bend_positive: prev.bend_positive,
+            compress: prev.compress,
+            stretch: prev.stretch,
+        };
+        *pose = pose.lerp(&target, alpha);
+    }
+}
+
+pub fn apply_transform_constraint_timeline(
+    keyframes: &[AnimationTransform],
+    time: f32,
+    alpha: f32,
+    pose: &mut TransformConstraintPose,
+) {
+    if let Some((prev, next, percent)) = sample(keyframes, time) {
+        let target = TransformConstraintPose {
+            rotate_mix: lerp(prev.rotate_mix, next.rotate_mix, percent),
+            translate_mix: lerp(prev.translate_mix, next.translate_mix, percent),
+            scale_mix: lerp(prev.scale_mix, next.scale_mix, percent),
+            shear_mix: lerp(prev.shear_mix, next.shear_mix, percent),
+        };
+        *pose = pose.lerp(&target, alpha);
+    }
+}
+
+pub fn apply_path_constraint_timeline(
+    timeline: &AnimationPath,
+    time: f32,
+    alpha: f32,
+    pose: &mut PathConstraintPose,
+) {
+    let mut target = *pose;
+
+    if let Some((prev, next, percent)) = sample(&timeline.position, time) {
+        target.position = lerp(prev.position, next.position, percent);
+    }
+
+    if let Some((prev, next, percent)) = sample(&timeline.spacing, time) {
+        target.spacing = lerp(prev.spacing, next.spacing, percent);
+    }
+
+    if let Some((prev, next, percent)) = sample(&timeline.mix, time) {
+        target.rotate_mix = lerp(prev.rotate_mix, next.rotate_mix, percent);
+        target.translate_mix = lerp(prev.translate_mix, next.translate_mix, percent);
+    }
+
+    *pose = pose.lerp(&target, alpha);
+}
+
+/// Attachment keyframes are stepped and only applied when `alpha` isn't 0,
+/// color and two color (light and dark) keyframes are interpolated
+pub fn apply_slot_timeline(timeline: &AnimationSlot, time: f32, alpha: f32, pose: &mut SlotPose) {
+    let mut target = pose.clone();
+
+    if let Some(keyframe) = timeline
+        .attachment
+        .iter()
+        .rev()
+        .find(|keyframe| keyframe.time <= time)
+    {
+        // Sequences start over at the setup frame when the attachment changes
+        if target.attachment != keyframe.name {
+            target.attachment = keyframe.name.clone();
+            target.sequence_index = None;
+        }
+    }
+
+    if let Some((prev, next, percent)) = sample(&timeline.color, time) {
+        let prev = Color::hex(&prev.color).unwrap_or(Color::WHITE);
+        let next = Color::hex(&next.color).unwrap_or(Color::WHITE);
+        target.color = lerp_color(prev, next, percent);
+    }
+
+    if let Some((prev, next, percent)) = sample(&timeline.two_color, time) {
+        let color = |hex: &str, default: Color| Color::hex(hex).unwrap_or(default);
+        target.color = lerp_color(
+            color(&prev.light, Color::WHITE),
+            color(&next.light, Color::WHITE),
+            percent,
+        );
+        target.dark = lerp_color(
+            color(&prev.dark, Color::BLACK),
+            color(&next.dark, Color::BLACK),
+            percent,
+        );
+    }
+
+    *pose = pose.lerp(&target, alpha);
+}
+
+/// Sequence keyframes are stepped and only applied when `alpha` isn't 0, the frame advances every
+/// keyframe `delay` seconds as given by the [`SequenceMode`] of a sequence with `count` frames
+pub fn apply_sequence_timeline(
+    keyframes: &[SequenceKeyframe],
+    count: usize,
+    time: f32,
+    alpha: f32,
+    sequence_index: &mut Option<usize>,
+) {
+    if alpha <= 0.0 || count == 0 {
+        return;
+    }
+
+    let keyframe = if let Some(keyframe) = keyframes.iter().rev().find(|k| k.time <= time) {
+        keyframe
+    } else {
+        return;
+    };
+
+    let mut index = keyframe.index;
+    if keyframe.mode != SequenceMode::Hold && keyframe.delay > 0.0 {
+        index += ((time - keyframe.time) / keyframe.delay + 0.0001) as usize;
+    }
+
+    // Frames going back and forth, without repeating the first and last frames
+    let pingpong = |index: usize| {
+        let n = (count << 1) - 2;
+        if n == 0 {
+            return 0;
+        }
+        let index = index % n;
+        if index >= count {
+            n - index
+        } else {
+            index
+        }
+    };
+
+    *sequence_index = Some(match keyframe.mode {
+        SequenceMode::Hold => index,
+        SequenceMode::Once => index.min(count - 1),
+        SequenceMode::Loop => index % count,
+        SequenceMode::Pingpong => pingpong(index),
+        SequenceMode::OnceReverse => (count - 1).saturating_sub(index),
+        SequenceMode::LoopReverse => count - 1 - index % count,
+        SequenceMode::PingpongReverse => pingpong(index + count - 1),
+    });
+}
+
+/// Draw order keyframes are stepped and only applied when `alpha` isn't 0,
+/// keyframes without offsets go back to the setup draw order
+pub fn apply_draw_order_timeline(
+    spine: &Spine,
+    keyframes: &[AnimationDrawOrder],
+    time: f32,
+    alpha: f32,
+    draw_order: &mut Vec<usize>,
+) {
+    if alpha <= 0.0 {
+        return;
+    }
+
+    if let Some(keyframe) = keyframes
+        .iter()
+        .rev()
+        .find(|keyframe| keyframe.time <= time)
+    {
+        let slots_count = spine.slots.len();
+        draw_order.clear();
+        draw_order.resize(slots_count, usize::MAX);
+
+        // Slots moved by the offsets are placed first, the others fill the remaining places
+        let mut unchanged = Vec::with_capacity(slots_count);
+        let mut original = 0;
+        for offset in &keyframe.offsets {
+            let slot = if let Some(slot) =
+                find_target(offset.target, &offset.slot, &spine.slots, |slot| &slot.name)
+            {
+                slot
+            } else {
+                continue;
+            };
+
+            while original < slot {
+                unchanged.push(original);
+                original += 1;
+            }
+
+            let index = original as i32 + offset.offset;
+            if let Some(place) = draw_order.get_mut(index.max(0) as usize) {
+                *place = original;
+            }
+            original += 1;
+        }
+        while original < slots_count {
+            unchanged.push(original);
+            original += 1;
+        }
+
+        for place in draw_order.iter_mut().rev() {
+            if *place == usize::MAX {
+                *place = unchanged.pop().unwrap_or_default();
+            }
+        }
+    }
+}
+
+/// Deform keyframes only store the values starting at their `offset`, the remaining values are 0
+pub fn apply_deform_timeline(
+    keyframes: &[AnimationDeform],
+    len: usize,
+    time: f32,
+    alpha: f32,
+    offsets: &mut Vec<Vec2>,
+) {
+    if let Some((prev, next, percent)) = sample(keyframes, time) {
+        let value = |keyframe: &AnimationDeform, i: usize| {
+            let start = keyframe.offset.max(0) as usize;
+            i.checked_sub(start)
+                .and_then(|i| keyframe.vertices.get(i))
+                .copied()
+                .unwrap_or(0.0)
+        };
+
+        let target: Vec<Vec2> = (0..len)
+            .map(|i| {
+                let x = lerp(value(prev, i * 2), value(next, i * 2), percent);
+                let y = lerp(value(prev, i * 2 + 1), value(next, i * 2 + 1), percent);
+                Vec2::new(x, y)
+            })
+            .collect();
+
+        lerp_offsets(offsets, &target, alpha);
+    }
+}
+
+/// Interpolates the deform `offsets` towards `target`, missing values are 0
+fn lerp_offsets(offsets: &mut Vec<Vec2>, target: &[Vec2], t: f32) {
+    if offsets.len() < target.len() {
+        offsets.resize(target.len(), Vec2::ZERO);
+    }
+
+    for (i, offset) in offsets.iter_mut().enumerate() {
+        let target = target.get(i).copied().unwrap_or(Vec2::ZERO);
+        *offset = offset.lerp(target, t);
+    }
+}
+
+/// Interpolates each color channel, as given by spine
+pub fn lerp_color(a: Color, b: Color, t: f32) -> Color {
+    Color::rgba(
+        lerp(a.r(), b.r(), t),
+        lerp(a.g(), b.g(), t),
+        lerp(a.b(), b.b(), t),
+        lerp(a.a(), b.a(), t),
+    )
+}
+
+/// Interpolates two transforms, rotation takes the shortest path
+pub fn lerp_transform(a: &Transform2D, b: &Transform2D, t: f32) -> Transform2D {
+    Transform2D {
+        translation: a.translation.lerp(b.translation, t),
+        rotation: lerp_degrees(a.rotation.to_degrees(), b.rotation.to_degrees(), t).to_radians(),
+        scale: a.scale.lerp(b.scale, t),
+        shear: a.shear.lerp(b.shear, t),
+    }
+}
+
+/// Time local to the animation
+pub fn animation_time(time: f32, duration: f32, looping: bool) -> f32 {
+    if looping && duration > 0.0 {
+        time.rem_euclid(duration)
+    } else {
+        time.min(duration)
+    }
+}
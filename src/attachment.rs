@@ -0,0 +1,632 @@
+use bevy::{
+    ecs::{
+        entity::{EntityMap, MapEntities, MapEntitiesError},
+        reflect::ReflectMapEntities,
+    },
+    prelude::*,
+    utils::HashSet,
+};
+
+use crate::{
+    animation::AnimationSystem,
+    bounds::skeleton_bounds_system,
+    clipping::clipping_system,
+    constraints::{ConstraintSystem, WorldTransform},
+    importer::DEFAULT_SKIN,
+    skin::skeleton_skin_system,
+    slot::draw_order_system,
+    spine::spine::{self, SkinAttachment},
+    sprite::Sprite,
+    transform::{LocalToWorld, LocalToWorld2D, Transform2D5System, TransformMode},
+};
+
+/// Vertices of an attachment, either relative to the slot bone or weighted by many bones
+#[derive(Default, Debug, Clone, Reflect)]
+pub struct AttachmentVertices {
+    /// Vertices positions, weighted vertices have one position per bone of influence
+    /// given in the bone space
+    pub positions: Vec<Vec2>,
+    /// Bone of each position, only used by weighted vertices
+    pub bones: Vec<Entity>,
+    /// Weight of each position, only used by weighted vertices
+    pub weights: Vec<f32>,
+    /// Number of bones of influence of each vertex, only used by weighted vertices
+    pub influences: Vec<u32>,
+}
+
+impl AttachmentVertices {
+    /// Reads the spine `vertices`, weighted vertices are given by `[bone_count, (bone_index, x, y, weight)*]`
+    /// where the bone index maps to the skeleton `bones` entities;
+    ///
+    /// Fails when the weighted vertices are truncated or reference a bone that doesn't exist
+    pub fn new(vertices: &[f32], vertex_count: usize, bones: &[Entity]) -> anyhow::Result<Self> {
+        let mut attachment_vertices = AttachmentVertices::default();
+
+        if vertices.len() == vertex_count * 2 {
+            attachment_vertices.positions = vertices
+                .chunks_exact(2)
+                .map(|v| Vec2::new(v[0], v[1]))
+                .collect();
+            return Ok(attachment_vertices);
+        }
+
+        let mut i = 0;
+        while i < vertices.len() {
+            let count = vertices[i] as usize;
+            i += 1;
+            anyhow::ensure!(
+                vertices.len() - i >= count * 4,
+                "expected {} bones of influence for vertex {}",
+                count,
+                attachment_vertices.influences.len()
+            );
+            attachment_vertices.influences.push(count as u32);
+            for v in vertices[i..i + count * 4].chunks_exact(4) {
+                let bone = v[0] as usize;
+                let bone = bones
+                    .get(bone)
+                    .copied()
+                    .ok_or_else(|| anyhow::anyhow!("bone {} not found", bone))?;
+                attachment_vertices.bones.push(bone);
+                attachment_vertices.positions.push(Vec2::new(v[1], v[2]));
+                attachment_vertices.weights.push(v[3]);
+            }
+            i += count * 4;
+        }
+
+        anyhow::ensure!(
+            attachment_vertices.influences.len() == vertex_count,
+            "expected {} vertices, found {}",
+            vertex_count,
+            attachment_vertices.influences.len()
+        );
+
+        Ok(attachment_vertices)
+    }
+
+    #[inline]
+    pub fn is_weighted(&self) -> bool {
+        !self.influences.is_empty()
+    }
+
+    /// Number of vertices
+    pub fn len(&self) -> usize {
+        if self.is_weighted() {
+            self.influences.len()
+        } else {
+            self.positions.len()
+        }
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of positions given by the spine `vertices`, also the length of the deform offsets
+    pub fn positions_len(vertices: &[f32], vertex_count: usize) -> usize {
+        if vertices.len() == vertex_count * 2 {
+            return vertex_count;
+        }
+
+        let mut len = 0;
+        let mut i = 0;
+        while i < vertices.len() {
+            let count = vertices[i] as usize;
+            len += count;
+            i += 1 + count * 4;
+        }
+        len
+    }
+
+    /// Computes the vertices world positions, `slot_world` is the world matrix of the slot (or its bone)
+    /// and `bone_world` gives the world matrix of bones influencing weighted vertices;
+    /// The `deform` offsets are added to the positions, when empty the positions are used as is
+    pub fn compute_world(
+        &self,
+        slot_world: &Mat3,
+        deform: &[Vec2],
+        mut bone_world: impl FnMut(Entity) -> Option<Mat3>,
+        world: &mut Vec<Vec2>,
+    ) {
+        world.clear();
+
+        let position = |i: usize| self.positions[i] + deform.get(i).copied().unwrap_or(Vec2::ZERO);
+
+        if !self.is_weighted() {
+            world.extend(
+                (0..self.positions.len()).map(|i| slot_world.transform_point2(position(i))),
+            );
+            return;
+        }
+
+        let mut i = 0;
+        for count in &self.influences {
+            let mut vertex = Vec2::ZERO;
+            for _ in 0..*count {
+                if let Some(matrix) = bone_world(self.bones[i]) {
+                    vertex += matrix.transform_point2(position(i)) * self.weights[i];
+                }
+                i += 1;
+            }
+            world.push(vertex);
+        }
+    }
+
+    fn map_entities(&mut self, entity_map: &EntityMap) -> Result<(), MapEntitiesError> {
+        for entity in &mut self.bones {
+            *entity = entity_map.get(*entity)?;
+        }
+        Ok(())
+    }
+}
+
+/// Inverse of the slot world matrix, used to bring world vertices back to the slot space;
+/// `None` when the slot is scaled to 0 since the vertices all collapse to a single point
+pub(crate) fn slot_world_inverse(slot_world: &Mat3) -> Option<Mat3> {
+    if slot_world.determinant() == 0.0 {
+        return None;
+    }
+    Some(slot_world.inverse()).filter(Mat3::is_finite)
+}
+
+/// Path shown by a slot, made of cubic bezier curves where each curve point has an incoming and outgoing
+/// control point, used by the [`FollowPathConstraint`](crate::constraints::FollowPathConstraint)
+#[derive(Default, Debug, Clone, Reflect)]
+#[reflect(Component, MapEntities)]
+pub struct PathAttachment {
+    /// Curves control points, given as `[in, point, out]` for each curve point
+    pub vertices: AttachmentVertices,
+    /// Last point is connected to the first
+    pub closed: bool,
+    /// Bones are evenly distributed along the path, regardless of the control points spacing
+    pub constant_speed: bool,
+    /// Length of each curve, in the setup pose
+    pub lengths: Vec<f32>,
+}
+
+impl PathAttachment {
+    /// Runtime data of a path `attachment`, `bones` are the skeleton bones entities
+    pub fn from_skin_attachment(attachment: &SkinAttachment, bones: &[Entity]) -> Option<Self> {
+        if let SkinAttachment::Path {
+            closed,
+            constant_speed,
+            lengths,
+            vertex_count,
+            vertices,
+            ..
+        } = attachment
+        {
+            Some(PathAttachment {
+                vertices: AttachmentVertices::new(vertices, *vertex_count, bones).ok()?,
+                closed: *closed,
+                constant_speed: *constant_speed,
+                lengths: lengths.clone(),
+            })
+        } else {
+            None
+        }
+    }
+}
+
+impl MapEntities for PathAttachment {
+    fn map_entities(&mut self, entity_map: &EntityMap) -> Result<(), MapEntitiesError> {
+        self.vertices.map_entities(entity_map)
+    }
+}
+
+/// Mesh shown by a slot, weighted or deformed meshes have their vertices updated every frame
+/// by the [`mesh_skinning_system`]
+#[derive(Default, Debug, Clone, Reflect)]
+#[reflect(Component, MapEntities)]
+pub struct MeshAttachment {
+    /// Skin of the attachment, deform timelines target attachments of a specific skin
+    pub skin: String,
+    /// Attachment name (in the skin)
+    pub name: String,
+    pub vertices: AttachmentVertices,
+    /// Offsets added to the vertices positions, written by the deform timelines
+    pub deform: Vec<Vec2>,
+}
+
+impl MeshAttachment {
+    /// Runtime data of a mesh (or linked mesh) `attachment` named `name` in the `skin`,
+    /// `bones` are the skeleton bones entities;
+    ///
+    /// Linked meshes that inherit the deform timelines are deformed as their parent mesh
+    pub fn from_skin_attachment(
+        skin: &str,
+        name: &str,
+        attachment: &SkinAttachment,
+        bones: &[Entity],
+    ) -> Option<Self> {
+        let (skin, name) = match attachment {
+            SkinAttachment::LinkedMesh {
+                skin: parent_skin,
+                parent,
+                deform: true,
+                ..
+            } => (
+                parent_skin.as_deref().unwrap_or(DEFAULT_SKIN),
+                parent.as_str(),
+            ),
+            _ => (skin, name),
+        };
+
+        if let Some(SkinAttachment::Mesh { uvs, vertices, .. }) = attachment.mesh() {
+            Some(MeshAttachment {
+                skin: skin.to_string(),
+                name: name.to_string(),
+                vertices: AttachmentVertices::new(vertices, uvs.len() / 2, bones).ok()?,
+                deform: vec![],
+            })
+        } else {
+            None
+        }
+    }
+}
+
+impl MapEntities for MeshAttachment {
+    fn map_entities(&mut self, entity_map: &EntityMap) -> Result<(), MapEntitiesError> {
+        self.vertices.map_entities(entity_map)
+    }
+}
+
+/// Polygon that clips the slots drawn after it, up to the `end` slot, see the [`clipping_system`]
+#[derive(Default, Debug, Clone, Reflect)]
+#[reflect(Component, MapEntities)]
+pub struct ClippingAttachment {
+    /// Clipping polygon, it may be concave
+    pub vertices: AttachmentVertices,
+    /// Index in the [`Skeleton::slots`](crate::Skeleton::slots) of the last clipped slot,
+    /// when `None` every slot drawn after the clipping slot is clipped
+    pub end: Option<usize>,
+}
+
+impl ClippingAttachment {
+    /// Runtime data of a clipping `attachment`, `slots` are the skeleton slots
+    /// and `bones` are the skeleton bones entities
+    pub fn from_skin_attachment(
+        attachment: &SkinAttachment,
+        slots: &[spine::Slot],
+        bones: &[Entity],
+    ) -> Option<Self> {
+        if let SkinAttachment::Clipping {
+            end,
+            vertex_count,
+            vertices,
+            ..
+        } = attachment
+        {
+            Some(ClippingAttachment {
+                vertices: AttachmentVertices::new(vertices, *vertex_count, bones).ok()?,
+                end: end
+                    .as_ref()
+                    .and_then(|end| slots.iter().position(|slot| slot.name == *end)),
+            })
+        } else {
+            None
+        }
+    }
+}
+
+impl MapEntities for ClippingAttachment {
+    fn map_entities(&mut self, entity_map: &EntityMap) -> Result<(), MapEntitiesError> {
+        self.vertices.map_entities(entity_map)
+    }
+}
+
+/// Polygon used for hit detection, its world vertices are found in the [`SkeletonBounds`](crate::SkeletonBounds)
+#[derive(Default, Debug, Clone, Reflect)]
+#[reflect(Component, MapEntities)]
+pub struct BoundingBoxAttachment {
+    pub vertices: AttachmentVertices,
+}
+
+impl BoundingBoxAttachment {
+    /// Runtime data of a bounding box `attachment`, `bones` are the skeleton bones entities
+    pub fn from_skin_attachment(attachment: &SkinAttachment, bones: &[Entity]) -> Option<Self> {
+        if let SkinAttachment::BoundingBox {
+            vertex_count,
+            vertices,
+            ..
+        } = attachment
+        {
+            Some(BoundingBoxAttachment {
+                vertices: AttachmentVertices::new(vertices, *vertex_count, bones).ok()?,
+            })
+        } else {
+            None
+        }
+    }
+}
+
+impl MapEntities for BoundingBoxAttachment {
+    fn map_entities(&mut self, entity_map: &EntityMap) -> Result<(), MapEntitiesError> {
+        self.vertices.map_entities(entity_map)
+    }
+}
+
+/// Point relative to the slot bone, used as a socket to spawn projectiles, particles and such;
+///
+/// Point slots are found by their [`Name`], the world position and rotation are given by [`PointAttachment::world`]
+/// ```rust,ignore
+/// fn muzzle_system(points: Query<(&PointAttachment, &LocalToWorld2D)>) {
+///     for (point, slot_world) in points.iter() {
+///         if point.name == "muzzle" {
+///             let (position, rotation) = point.world(&slot_world.0);
+///         }
+///     }
+/// }
+/// ```
+#[derive(Default, Debug, Clone, Reflect)]
+#[reflect(Component)]
+pub struct PointAttachment {
+    /// Attachment name (in the skin)
+    pub name: String,
+    pub position: Vec2,
+    /// Rotation in radians
+    pub rotation: f32,
+}
+
+impl PointAttachment {
+    /// Runtime data of a point `attachment` named `name`
+    pub fn from_skin_attachment(name: &str, attachment: &SkinAttachment) -> Option<Self> {
+        if let SkinAttachment::Point { x, y, rotation, .. } = attachment {
+            Some(PointAttachment {
+                name: name.to_string(),
+                position: Vec2::new(*x, *y),
+                rotation: rotation.to_radians(),
+            })
+        } else {
+            None
+        }
+    }
+
+    /// World position and rotation (in radians) of the point, `slot_world` is the world matrix of the slot
+    pub fn world(&self, slot_world: &Mat3) -> (Vec2, f32) {
+        let direction =
+            slot_world.transform_vector2(Vec2::new(self.rotation.cos(), self.rotation.sin()));
+        (
+            slot_world.transform_point2(self.position),
+            direction.y.atan2(direction.x),
+        )
+    }
+}
+
+/// Skins and deforms the [`MeshAttachment`]s, each slot gets its own copy of the sprite mesh
+/// that is restored to the undeformed vertices once the slot isn't deformed anymore
+pub fn mesh_skinning_system<M: WorldTransform>(
+    mut meshes: ResMut<Assets<Mesh>>,
+    sprites: Res<Assets<Sprite>>,
+    mut slots: Query<(
+        Entity,
+        &MeshAttachment,
+        &M,
+        &Handle<Sprite>,
+        &mut Handle<Mesh>,
+    )>,
+    bones: Query<&M>,
+    mut deformed: Local<HashSet<Entity>>,
+    mut world: Local<Vec<Vec2>>,
+) {
+    let mut deformed_now = HashSet::default();
+
+    for (entity, attachment, slot_world, sprite, mut mesh_handle) in slots.iter_mut() {
+        if !attachment.vertices.is_weighted() && attachment.deform.is_empty() {
+            continue;
+        }
+
+        // Sprite mesh is shared by every entity using the same sprite
+        let sprite_mesh = if let Some(sprite_mesh) = sprites.get(sprite).and_then(Sprite::mesh) {
+            sprite_mesh
+        } else {
+            continue;
+        };
+        if *sprite_mesh == *mesh_handle {
+            let mesh = if let Some(mesh) = meshes.get(sprite_mesh) {
+                mesh.clone()
+            } else {
+                continue;
+            };
+            *mesh_handle = meshes.add(mesh);
+        }
+        deformed_now.insert(entity);
+
+        let slot_world = slot_world.matrix_2d();
+        attachment.vertices.compute_world(
+            &slot_world,
+            &attachment.deform,
+            |bone| bones.get(bone).ok().map(WorldTransform::matrix_2d),
+            &mut world,
+        );
+
+        // Vertices are given in the slot space, keeps the last ones while the slot is scaled to 0
+        let slot_world_inv = if let Some(slot_world_inv) = slot_world_inverse(&slot_world) {
+            slot_world_inv
+        } else {
+            continue;
+        };
+        let positions: Vec<[f32; 2]> = world
+            .iter()
+            .map(|vertex| slot_world_inv.transform_point2(*vertex).into())
+            .collect();
+
+        if let Some(mesh) = meshes.get_mut(&*mesh_handle) {
+            mesh.set_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        }
+    }
+
+    // Restore the vertices of the slots that aren't deformed anymore
+    for entity in deformed.difference(&deformed_now) {
+        if let Ok((_, _, _, sprite, mesh_handle)) = slots.get_mut(*entity) {
+            let sprite_mesh = if let Some(sprite_mesh) = sprites.get(sprite).and_then(Sprite::mesh)
+            {
+                sprite_mesh
+            } else {
+                continue;
+            };
+            if *sprite_mesh == *mesh_handle {
+                continue;
+            }
+
+            let positions = meshes
+                .get(sprite_mesh)
+                .and_then(|mesh| mesh.attribute(Mesh::ATTRIBUTE_POSITION))
+                .cloned();
+            if let (Some(positions), Some(mesh)) = (positions, meshes.get_mut(&*mesh_handle)) {
+                mesh.set_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+            }
+        }
+    }
+
+    *deformed = deformed_now;
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+pub enum AttachmentSystem {
+    MeshSkinning,
+}
+
+/// Adds the [`skeleton_skin_system`], the [`mesh_skinning_system`], [`clipping_system`] and
+/// [`skeleton_bounds_system`] matching the [`TransformMode`] and the [`draw_order_system`] (2.5D only),
+/// they run in the [`CoreStage::PostUpdate`], slots attachments are looked up in the skins after the animations
+/// while meshes are skinned, clipped, bounds are computed and slots are ordered after the constraints are applied
+#[derive(Default)]
+pub struct SpineAttachmentPlugin {
+    pub transform_mode: TransformMode,
+}
+
+impl Plugin for SpineAttachmentPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system_to_stage(
+            CoreStage::PostUpdate,
+            skeleton_skin_system
+                .system()
+                .after(AnimationSystem::Player)
+                .after(AnimationSystem::State)
+                .before(ConstraintSystem::Apply),
+        );
+
+        match self.transform_mode {
+            TransformMode::Transform2D => add_attachment_systems::<LocalToWorld2D>(app),
+            TransformMode::Transform2D5 => {
+                add_attachment_systems::<LocalToWorld>(app);
+                app.add_system_to_stage(
+                    CoreStage::PostUpdate,
+                    draw_order_system
+                        .system()
+                        .after(Transform2D5System::PropagateTransform2D)
+                        .after(ConstraintSystem::Apply),
+                );
+            }
+        }
+    }
+}
+
+fn add_attachment_systems<M: WorldTransform>(app: &mut AppBuilder) {
+    app.add_system_to_stage(
+        CoreStage::PostUpdate,
+        mesh_skinning_system::<M>
+            .system()
+            .label(AttachmentSystem::MeshSkinning)
+            .after(Transform2D5System::PropagateTransform2D)
+            .after(ConstraintSystem::Apply),
+    )
+    .add_system_to_stage(
+        CoreStage::PostUpdate,
+        clipping_system::<M>
+            .system()
+            .after(AttachmentSystem::MeshSkinning),
+    )
+    .add_system_to_stage(
+        CoreStage::PostUpdate,
+        skeleton_bounds_system::<M>
+            .system()
+            .after(Transform2D5System::PropagateTransform2D)
+            .after(ConstraintSystem::Apply),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn weighted_vertices() {
+        let bones = [Entity::new(0), Entity::new(1)];
+        let bones_world = [
+            Mat3::from_translation(Vec2::new(10.0, 0.0)),
+            Mat3::from_scale_angle_translation(Vec2::ONE, std::f32::consts::FRAC_PI_2, Vec2::ZERO),
+        ];
+
+        // First vertex is bound to a single bone, the second is split between both
+        let vertices = AttachmentVertices::new(
+            &[
+                1.0, 0.0, 1.0, 2.0, 1.0, //
+                2.0, 0.0, 0.0, 0.0, 0.5, 1.0, 4.0, 0.0, 0.5,
+            ],
+            2,
+            &bones,
+        )
+        .unwrap();
+        assert!(vertices.is_weighted());
+        assert_eq!(vertices.len(), 2);
+
+        let mut world = vec![];
+        vertices.compute_world(
+            &Mat3::IDENTITY,
+            &[],
+            |bone| Some(bones_world[bone.id() as usize]),
+            &mut world,
+        );
+        assert!(world[0].abs_diff_eq(Vec2::new(11.0, 2.0), 1e-5));
+        assert!(world[1].abs_diff_eq(Vec2::new(5.0, 2.0), 1e-5));
+    }
+
+    #[test]
+    fn invalid_weighted_vertices() {
+        let bones = [Entity::new(0), Entity::new(1)];
+
+        // Truncated bone of influence
+        assert!(AttachmentVertices::new(&[2.0, 0.0, 0.0, 0.0, 0.5, 1.0, 4.0], 1, &bones).is_err());
+        // Unknown bone
+        assert!(AttachmentVertices::new(&[1.0, 2.0, 0.0, 0.0, 1.0], 1, &bones).is_err());
+        // Missing vertex
+        assert!(AttachmentVertices::new(&[1.0, 1.0, 0.0, 0.0, 1.0], 2, &bones).is_err());
+    }
+
+    #[test]
+    fn slot_scaled_to_zero() {
+        let slot_world = Mat3::from_scale_angle_translation(
+            Vec2::new(2.0, 0.5),
+            std::f32::consts::FRAC_PI_4,
+            Vec2::new(10.0, 0.0),
+        );
+        let slot_world_inv = slot_world_inverse(&slot_world).unwrap();
+        assert!((slot_world_inv * slot_world).abs_diff_eq(Mat3::IDENTITY, 1e-5));
+
+        let slot_world =
+            Mat3::from_scale_angle_translation(Vec2::new(0.0, 1.0), 0.0, Vec2::new(10.0, 0.0));
+        assert!(slot_world_inverse(&slot_world).is_none());
+    }
+
+    #[test]
+    fn point_world() {
+        let point = PointAttachment {
+            name: "muzzle".to_string(),
+            position: Vec2::new(2.0, 0.0),
+            rotation: std::f32::consts::FRAC_PI_4,
+        };
+
+        // Non uniform scale skews the point rotation
+        let slot_world = Mat3::from_scale_angle_translation(
+            Vec2::new(1.0, 2.0),
+            std::f32::consts::FRAC_PI_2,
+            Vec2::new(10.0, 0.0),
+        );
+        let (position, rotation) = point.world(&slot_world);
+        assert!(position.abs_diff_eq(Vec2::new(10.0, 2.0), 1e-5));
+        assert!((rotation - 1.0f32.atan2(-2.0)).abs() < 1e-5);
+    }
+}
@@ -0,0 +1,208 @@
+use bevy::prelude::*;
+
+use crate::{
+    attachment::BoundingBoxAttachment, constraints::WorldTransform, skeleton::Skeleton, slot::Slot,
+};
+
+/// World polygon of a [`BoundingBoxAttachment`]
+#[derive(Debug, Clone)]
+pub struct BoundingPolygon {
+    /// Slot entity showing the bounding box
+    pub slot: Entity,
+    /// Bounding box attachment name
+    pub name: String,
+    pub vertices: Vec<Vec2>,
+}
+
+impl BoundingPolygon {
+    /// Polygon contains the `point`, polygons may be concave
+    pub fn contains_point(&self, point: Vec2) -> bool {
+        let mut inside = false;
+        let n = self.vertices.len();
+        for i in 0..n {
+            let a = self.vertices[i];
+            let b = self.vertices[(i + n - 1) % n];
+            if (a.y < point.y && b.y >= point.y) || (b.y < point.y && a.y >= point.y) {
+                let x = a.x + (point.y - a.y) / (b.y - a.y) * (b.x - a.x);
+                if x < point.x {
+                    inside = !inside;
+                }
+            }
+        }
+        inside
+    }
+
+    /// Segment from `start` to `end` crosses any of the polygon edges
+    pub fn intersects_segment(&self, start: Vec2, end: Vec2) -> bool {
+        let n = self.vertices.len();
+        (0..n).any(|i| segments_intersect(start, end, self.vertices[i], self.vertices[(i + 1) % n]))
+    }
+}
+
+/// World polygons of every bounding box shown by a [`Skeleton`] and their axis aligned bounding box,
+/// found in the root entity of the [`SpineSkeleton::scene`](crate::SpineSkeleton::scene),
+/// updated every frame by the [`skeleton_bounds_system`]
+#[derive(Default, Debug, Clone, Reflect)]
+#[reflect(Component)]
+pub struct SkeletonBounds {
+    #[reflect(ignore)]
+    pub polygons: Vec<BoundingPolygon>,
+    pub min: Vec2,
+    pub max: Vec2,
+}
+
+impl SkeletonBounds {
+    /// Axis aligned bounding box contains the `point`
+    pub fn aabb_contains_point(&self, point: Vec2) -> bool {
+        point.x >= self.min.x
+            && point.x <= self.max.x
+            && point.y >= self.min.y
+            && point.y <= self.max.y
+    }
+
+    /// Segment from `start` to `end` may cross the axis aligned bounding box
+    pub fn aabb_intersects_segment(&self, start: Vec2, end: Vec2) -> bool {
+        if self.aabb_contains_point(start) || self.aabb_contains_point(end) {
+            return true;
+        }
+
+        let corners = [
+            self.min,
+            Vec2::new(self.max.x, self.min.y),
+            self.max,
+            Vec2::new(self.min.x, self.max.y),
+        ];
+        (0..4).any(|i| segments_intersect(start, end, corners[i], corners[(i + 1) % 4]))
+    }
+
+    /// Axis aligned bounding box overlaps the one of the `other` skeleton
+    pub fn aabb_intersects(&self, other: &SkeletonBounds) -> bool {
+        self.min.x < other.max.x
+            && self.max.x > other.min.x
+            && self.min.y < other.max.y
+            && self.max.y > other.min.y
+    }
+
+    /// First bounding polygon that contains the `point`
+    pub fn contains_point(&self, point: Vec2) -> Option<&BoundingPolygon> {
+        if !self.aabb_contains_point(point) {
+            return None;
+        }
+        self.polygons
+            .iter()
+            .find(|polygon| polygon.contains_point(point))
+    }
+
+    /// First bounding polygon crossed by the segment from `start` to `end`
+    pub fn intersects_segment(&self, start: Vec2, end: Vec2) -> Option<&BoundingPolygon> {
+        if !self.aabb_intersects_segment(start, end) {
+            return None;
+        }
+        self.polygons
+            .iter()
+            .find(|polygon| polygon.intersects_segment(start, end))
+    }
+
+    /// Bounding polygon shown by the `slot`
+    pub fn polygon(&self, slot: Entity) -> Option<&BoundingPolygon> {
+        self.polygons.iter().find(|polygon| polygon.slot == slot)
+    }
+}
+
+/// Computes the world polygons of the [`SkeletonBounds`], from the slots world transform
+pub fn skeleton_bounds_system<M: WorldTransform>(
+    mut skeletons: Query<(&Skeleton, &mut SkeletonBounds)>,
+    bounding_boxes: Query<(&BoundingBoxAttachment, &Slot, &M)>,
+    bones: Query<&M>,
+) {
+    for (skeleton, mut bounds) in skeletons.iter_mut() {
+        bounds.polygons.clear();
+
+        for slot_entity in &skeleton.slots {
+            if let Ok((bounding_box, slot, slot_world)) = bounding_boxes.get(*slot_entity) {
+                let mut vertices = vec![];
+                bounding_box.vertices.compute_world(
+                    &slot_world.matrix_2d(),
+                    &[],
+                    |bone| bones.get(bone).ok().map(WorldTransform::matrix_2d),
+                    &mut vertices,
+                );
+                bounds.polygons.push(BoundingPolygon {
+                    slot: *slot_entity,
+                    name: slot.attachment.clone().unwrap_or_default(),
+                    vertices,
+                });
+            }
+        }
+
+        let mut min = Vec2::splat(f32::MAX);
+        let mut max = Vec2::splat(f32::MIN);
+        for vertex in bounds
+            .polygons
+            .iter()
+            .flat_map(|polygon| polygon.vertices.iter())
+        {
+            min = min.min(*vertex);
+            max = max.max(*vertex);
+        }
+        if min.x > max.x {
+            // No bounding boxes
+            min = Vec2::ZERO;
+            max = Vec2::ZERO;
+        }
+
+        bounds.min = min;
+        bounds.max = max;
+    }
+}
+
+fn segments_intersect(a: Vec2, b: Vec2, c: Vec2, d: Vec2) -> bool {
+    let cross = |u: Vec2, v: Vec2| u.x * v.y - u.y * v.x;
+    let ab = b - a;
+    let cd = d - c;
+    let denominator = cross(ab, cd);
+    if denominator == 0.0 {
+        return false;
+    }
+
+    let t = cross(c - a, cd) / denominator;
+    let u = cross(c - a, ab) / denominator;
+    (0.0..=1.0).contains(&t) && (0.0..=1.0).contains(&u)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bounding_polygon_queries() {
+        // Concave arrow pointing right
+        let polygon = BoundingPolygon {
+            slot: Entity::new(0),
+            name: "hitbox".to_string(),
+            vertices: vec![
+                Vec2::new(0.0, 0.0),
+                Vec2::new(4.0, 2.0),
+                Vec2::new(0.0, 4.0),
+                Vec2::new(1.0, 2.0),
+            ],
+        };
+        let bounds = SkeletonBounds {
+            polygons: vec![polygon],
+            min: Vec2::new(0.0, 0.0),
+            max: Vec2::new(4.0, 4.0),
+        };
+
+        assert!(bounds.contains_point(Vec2::new(2.0, 1.5)).is_some());
+        assert!(bounds.contains_point(Vec2::new(0.5, 1.5)).is_none());
+        assert!(bounds.contains_point(Vec2::new(5.0, 1.5)).is_none());
+        assert!(bounds.aabb_contains_point(Vec2::new(0.5, 1.5)));
+
+        let hit = bounds.intersects_segment(Vec2::new(2.0, -1.0), Vec2::new(2.0, 5.0));
+        assert_eq!(hit.map(|polygon| polygon.name.as_str()), Some("hitbox"));
+        assert!(bounds
+            .intersects_segment(Vec2::new(-1.0, 5.0), Vec2::new(5.0, 5.0))
+            .is_none());
+        assert!(bounds.aabb_intersects_segment(Vec2::new(-1.0, 2.0), Vec2::new(5.0, 3.0)));
+    }
+}
@@ -0,0 +1,437 @@
+use bevy::{
+    prelude::*,
+    render::mesh::{Indices, VertexAttributeValues},
+    utils::HashSet,
+};
+
+use crate::{
+    attachment::{slot_world_inverse, ClippingAttachment, MeshAttachment},
+    constraints::WorldTransform,
+    skeleton::Skeleton,
+    slot::DrawOrder,
+    sprite::Sprite,
+};
+
+/// Mesh vertices and triangles, positions are given in the slot space
+#[derive(Default, Debug, Clone)]
+pub struct ClippedMesh {
+    pub positions: Vec<Vec2>,
+    pub uvs: Vec<Vec2>,
+    pub indices: Vec<u32>,
+}
+
+/// Splits the `polygon` into triangles by ear clipping, the polygon may be concave and have any winding,
+/// the triangles are given in counter-clockwise order
+pub fn triangulate(polygon: &[Vec2]) -> Vec<[Vec2; 3]> {
+    let mut triangles = vec![];
+    if polygon.len() < 3 {
+        return triangles;
+    }
+
+    let mut remaining: Vec<usize> = (0..polygon.len()).collect();
+    if signed_area(polygon) < 0.0 {
+        remaining.reverse();
+    }
+
+    while remaining.len() > 3 {
+        let n = remaining.len();
+        let ear = (0..n).find(|&i| {
+            let a = polygon[remaining[(i + n - 1) % n]];
+            let b = polygon[remaining[i]];
+            let c = polygon[remaining[(i + 1) % n]];
+            cross(b - a, c - b) > 0.0
+                && remaining.iter().all(|&j| {
+                    let p = polygon[j];
+                    p == a || p == b || p == c || !point_in_triangle(p, a, b, c)
+                })
+        });
+
+        // Degenerated polygons may not have an ear, remove any vertex to keep going
+        let i = ear.unwrap_or(0);
+        let a = polygon[remaining[(i + n - 1) % n]];
+        let b = polygon[remaining[i]];
+        let c = polygon[remaining[(i + 1) % n]];
+        if ear.is_some() {
+            triangles.push([a, b, c]);
+        }
+        remaining.remove(i);
+    }
+
+    let triangle = [
+        polygon[remaining[0]],
+        polygon[remaining[1]],
+        polygon[remaining[2]],
+    ];
+    if cross(triangle[1] - triangle[0], triangle[2] - triangle[1]) > 0.0 {
+        triangles.push(triangle);
+    }
+
+    triangles
+}
+
+/// Clips the `subject` polygon by the convex `clip` polygon (Sutherland-Hodgman),
+/// `clip` must be counter-clockwise while the `subject` keeps its own winding
+pub fn clip_polygon(subject: &[Vec2], clip: &[Vec2], output: &mut Vec<Vec2>) {
+    output.clear();
+    output.extend_from_slice(subject);
+
+    let mut input = vec![];
+    for (i, &edge_start) in clip.iter().enumerate() {
+        let edge_end = clip[(i + 1) % clip.len()];
+        let edge = edge_end - edge_start;
+        let inside = |p: Vec2| cross(edge, p - edge_start) >= 0.0;
+
+        std::mem::swap(&mut input, output);
+        output.clear();
+        for (j, &current) in input.iter().enumerate() {
+            let previous = input[(j + input.len() - 1) % input.len()];
+            match (inside(previous), inside(current)) {
+                (true, true) => output.push(current),
+                (true, false) => output.push(intersection(previous, current, edge_start, edge)),
+                (false, true) => {
+                    output.push(intersection(previous, current, edge_start, edge));
+                    output.push(current);
+                }
+                (false, false) => {}
+            }
+        }
+
+        if output.is_empty() {
+            return;
+        }
+    }
+}
+
+/// Clips the triangles of a mesh by the `clip` triangles (given by [`triangulate`]),
+/// the uvs are interpolated inside each clipped triangle
+pub fn clip_mesh(
+    clip: &[[Vec2; 3]],
+    positions: &[Vec2],
+    uvs: &[Vec2],
+    indices: &[u32],
+    output: &mut ClippedMesh,
+) {
+    output.positions.clear();
+    output.uvs.clear();
+    output.indices.clear();
+
+    let mut polygon = vec![];
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [
+            triangle[0] as usize,
+            triangle[1] as usize,
+            triangle[2] as usize,
+        ];
+        let (pa, pb, pc) = (positions[a], positions[b], positions[c]);
+        let area = cross(pb - pa, pc - pa);
+        if area == 0.0 {
+            continue;
+        }
+
+        for clip_triangle in clip {
+            clip_polygon(&[pa, pb, pc], clip_triangle, &mut polygon);
+            if polygon.len() < 3 {
+                continue;
+            }
+
+            // Triangle fan of the clipped polygon
+            let first = output.positions.len() as u32;
+            for p in &polygon {
+                let wb = cross(pc - pa, *p - pa) / -area;
+                let wc = cross(pb - pa, *p - pa) / area;
+                let wa = 1.0 - wb - wc;
+                output.positions.push(*p);
+                output.uvs.push(uvs[a] * wa + uvs[b] * wb + uvs[c] * wc);
+            }
+            for i in 1..(polygon.len() as u32 - 1) {
+                output
+                    .indices
+                    .extend_from_slice(&[first, first + i, first + i + 1]);
+            }
+        }
+    }
+}
+
+/// Clips the meshes of the slots between each [`ClippingAttachment`] and its end slot (in the draw order),
+/// each clipped slot gets its own copy of the sprite mesh, that is restored once the slot isn't clipped anymore
+pub fn clipping_system<M: WorldTransform>(
+    mut meshes: ResMut<Assets<Mesh>>,
+    sprites: Res<Assets<Sprite>>,
+    skeletons: Query<&Skeleton>,
+    clippings: Query<(&ClippingAttachment, &M)>,
+    draw_orders: Query<&DrawOrder>,
+    mut slots: Query<(
+        &M,
+        &Visible,
+        &Handle<Sprite>,
+        &mut Handle<Mesh>,
+        Option<&MeshAttachment>,
+    )>,
+    bones: Query<&M>,
+    mut clipped: Local<HashSet<Entity>>,
+    mut world: Local<Vec<Vec2>>,
+    mut clipped_mesh: Local<ClippedMesh>,
+) {
+    let mut clipped_now = HashSet::default();
+
+    for skeleton in skeletons.iter() {
+        let mut draw_order: Vec<(usize, Entity)> = skeleton
+            .slots
+            .iter()
+            .map(|slot| (draw_orders.get(*slot).map_or(0, |order| order.0), *slot))
+            .collect();
+        draw_order.sort_by_key(|(order, _)| *order);
+
+        let mut clip: Option<(Option<Entity>, Vec<[Vec2; 3]>)> = None;
+        for (_, entity) in draw_order {
+            if let Ok((clipping, clip_world)) = clippings.get(entity) {
+                if clip.is_none() {
+                    clipping.vertices.compute_world(
+                        &clip_world.matrix_2d(),
+                        &[],
+                        |bone| bones.get(bone).ok().map(WorldTransform::matrix_2d),
+                        &mut world,
+                    );
+                    let end = clipping
+                        .end
+                        .and_then(|end| skeleton.slots.get(end).copied());
+                    clip = Some((end, triangulate(&world)));
+                }
+            } else if let Some((_, clip_triangles)) = &clip {
+                let slot = slots
+                    .get_mut(entity)
+                    .ok()
+                    .filter(|(_, visible, ..)| visible.is_visible);
+                if let Some((slot_world, _, sprite, mut mesh_handle, attachment)) = slot {
+                    let slot_world = slot_world.matrix_2d();
+                    let mesh = slot_mesh(
+                        &meshes,
+                        &sprites,
+                        sprite,
+                        &slot_world,
+                        attachment,
+                        |bone| bones.get(bone).ok().map(WorldTransform::matrix_2d),
+                        &mut world,
+                    );
+
+                    // Nothing to clip while the slot is scaled to 0
+                    let slot_world_inv = slot_world_inverse(&slot_world);
+                    if let (Some(mut mesh), Some(slot_world_inv)) = (mesh, slot_world_inv) {
+                        // Clipped in world space
+                        for position in &mut mesh.positions {
+                            *position = slot_world.transform_point2(*position);
+                        }
+                        clip_mesh(
+                            clip_triangles,
+                            &mesh.positions,
+                            &mesh.uvs,
+                            &mesh.indices,
+                            &mut clipped_mesh,
+                        );
+                        for position in &mut clipped_mesh.positions {
+                            *position = slot_world_inv.transform_point2(*position);
+                        }
+
+                        set_slot_mesh(
+                            &mut meshes,
+                            &sprites,
+                            sprite,
+                            &mut mesh_handle,
+                            &clipped_mesh,
+                        );
+                        clipped_now.insert(entity);
+                    }
+                }
+            }
+
+            // The end slot is also clipped
+            if clip.as_ref().map_or(false, |(end, _)| *end == Some(entity)) {
+                clip = None;
+            }
+        }
+    }
+
+    // Restore the meshes of the slots that aren't clipped anymore
+    for entity in clipped.difference(&clipped_now) {
+        if let Ok((slot_world, _, sprite, mut mesh_handle, attachment)) = slots.get_mut(*entity) {
+            if let Some(mesh) = slot_mesh(
+                &meshes,
+                &sprites,
+                sprite,
+                &slot_world.matrix_2d(),
+                attachment,
+                |bone| bones.get(bone).ok().map(WorldTransform::matrix_2d),
+                &mut world,
+            ) {
+                set_slot_mesh(&mut meshes, &sprites, sprite, &mut mesh_handle, &mesh);
+            }
+        }
+    }
+
+    *clipped = clipped_now;
+}
+
+/// Unclipped slot mesh, weighted or deformed vertices are computed just like the
+/// [`mesh_skinning_system`](crate::mesh_skinning_system) does
+fn slot_mesh(
+    meshes: &Assets<Mesh>,
+    sprites: &Assets<Sprite>,
+    sprite: &Handle<Sprite>,
+    slot_world: &Mat3,
+    attachment: Option<&MeshAttachment>,
+    bone_world: impl FnMut(Entity) -> Option<Mat3>,
+    world: &mut Vec<Vec2>,
+) -> Option<ClippedMesh> {
+    let mesh = meshes.get(sprites.get(sprite)?.mesh()?)?;
+
+    let mut positions: Vec<Vec2> = match mesh.attribute(Mesh::ATTRIBUTE_POSITION)? {
+        VertexAttributeValues::Float2(positions) => {
+            positions.iter().map(|p| Vec2::from(*p)).collect()
+        }
+        _ => return None,
+    };
+    let uvs = match mesh.attribute(Mesh::ATTRIBUTE_UV_0)? {
+        VertexAttributeValues::Float2(uvs) => uvs.iter().map(|uv| Vec2::from(*uv)).collect(),
+        _ => return None,
+    };
+    let indices = match mesh.indices()? {
+        Indices::U16(indices) => indices.iter().map(|i| *i as u32).collect(),
+        Indices::U32(indices) => indices.clone(),
+    };
+
+    if let Some(attachment) = attachment {
+        if attachment.vertices.is_weighted() || !attachment.deform.is_empty() {
+            attachment
+                .vertices
+                .compute_world(slot_world, &attachment.deform, bone_world, world);
+            let slot_world_inv = slot_world_inverse(slot_world)?;
+            positions = world
+                .iter()
+                .map(|vertex| slot_world_inv.transform_point2(*vertex))
+                .collect();
+        }
+    }
+
+    Some(ClippedMesh {
+        positions,
+        uvs,
+        indices,
+    })
+}
+
+/// Writes the slot mesh, copying the sprite mesh when it's shared
+fn set_slot_mesh(
+    meshes: &mut Assets<Mesh>,
+    sprites: &Assets<Sprite>,
+    sprite: &Handle<Sprite>,
+    mesh_handle: &mut Handle<Mesh>,
+    mesh: &ClippedMesh,
+) {
+    let sprite_mesh = if let Some(sprite_mesh) = sprites.get(sprite).and_then(Sprite::mesh) {
+        sprite_mesh
+    } else {
+        return;
+    };
+    if *sprite_mesh == *mesh_handle {
+        let sprite_mesh = if let Some(sprite_mesh) = meshes.get(sprite_mesh) {
+            sprite_mesh.clone()
+        } else {
+            return;
+        };
+        *mesh_handle = meshes.add(sprite_mesh);
+    }
+
+    if let Some(slot_mesh) = meshes.get_mut(&*mesh_handle) {
+        let positions: Vec<[f32; 2]> = mesh.positions.iter().map(|p| (*p).into()).collect();
+        let uvs: Vec<[f32; 2]> = mesh.uvs.iter().map(|uv| (*uv).into()).collect();
+        slot_mesh.set_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        slot_mesh.set_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+        slot_mesh.set_indices(Some(Indices::U32(mesh.indices.clone())));
+    }
+}
+
+#[inline]
+fn cross(a: Vec2, b: Vec2) -> f32 {
+    a.x * b.y - a.y * b.x
+}
+
+fn signed_area(polygon: &[Vec2]) -> f32 {
+    let mut area = 0.0;
+    for (i, a) in polygon.iter().enumerate() {
+        area += cross(*a, polygon[(i + 1) % polygon.len()]);
+    }
+    area * 0.5
+}
+
+fn point_in_triangle(p: Vec2, a: Vec2, b: Vec2, c: Vec2) -> bool {
+    cross(b - a, p - a) >= 0.0 && cross(c - b, p - b) >= 0.0 && cross(a - c, p - c) >= 0.0
+}
+
+/// Intersection of the segment `from` `to` with the line given by `origin` and `direction`
+fn intersection(from: Vec2, to: Vec2, origin: Vec2, direction: Vec2) -> Vec2 {
+    let d = to - from;
+    let t = cross(direction, origin - from) / cross(direction, d);
+    from + d * t
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn area(mesh: &ClippedMesh) -> f32 {
+        mesh.indices
+            .chunks_exact(3)
+            .map(|t| {
+                let [a, b, c] = [
+                    mesh.positions[t[0] as usize],
+                    mesh.positions[t[1] as usize],
+                    mesh.positions[t[2] as usize],
+                ];
+                cross(b - a, c - a).abs() * 0.5
+            })
+            .sum()
+    }
+
+    #[test]
+    fn clip_concave_polygon() {
+        // L shaped clipping polygon, clockwise
+        let polygon = [
+            Vec2::new(0.0, 0.0),
+            Vec2::new(0.0, 2.0),
+            Vec2::new(1.0, 2.0),
+            Vec2::new(1.0, 1.0),
+            Vec2::new(2.0, 1.0),
+            Vec2::new(2.0, 0.0),
+        ];
+        let clip = triangulate(&polygon);
+        assert_eq!(clip.len(), 4);
+        let clip_area: f32 = clip
+            .iter()
+            .map(|[a, b, c]| cross(*b - *a, *c - *a) * 0.5)
+            .sum();
+        assert!((clip_area - 3.0).abs() < 1e-5);
+
+        // Quad covering the polygon, partially outside of it
+        let positions = [
+            Vec2::new(-1.0, -1.0),
+            Vec2::new(3.0, -1.0),
+            Vec2::new(3.0, 3.0),
+            Vec2::new(-1.0, 3.0),
+        ];
+        let uvs = [
+            Vec2::new(0.0, 1.0),
+            Vec2::new(1.0, 1.0),
+            Vec2::new(1.0, 0.0),
+            Vec2::new(0.0, 0.0),
+        ];
+        let mut mesh = ClippedMesh::default();
+        clip_mesh(&clip, &positions, &uvs, &[0, 1, 2, 0, 2, 3], &mut mesh);
+
+        assert!((area(&mesh) - 3.0).abs() < 1e-4);
+        for (p, uv) in mesh.positions.iter().zip(&mesh.uvs) {
+            assert!(p.x > -1e-5 && p.x < 2.0 + 1e-5 && p.y > -1e-5 && p.y < 2.0 + 1e-5);
+            let expected = Vec2::new((p.x + 1.0) / 4.0, 1.0 - (p.y + 1.0) / 4.0);
+            assert!(uv.abs_diff_eq(expected, 1e-5));
+        }
+    }
+}
@@ -0,0 +1,517 @@
+use std::f32::consts::{FRAC_PI_2, PI};
+
+use bevy::{
+    ecs::{
+        entity::{EntityMap, MapEntities, MapEntitiesError},
+        reflect::ReflectMapEntities,
+    },
+    prelude::*,
+};
+
+use super::{BoneTransforms, WorldTransform};
+use crate::transform::Transform2D;
+
+/// Copies the transform of the `target` bone to the constrained `bones`, what is copied and by
+/// how much is given by the [`CopyRotationConstraint`], [`CopyPositionConstraint`],
+/// [`CopyScaleConstraint`] and [`CopyShearConstraint`] found in the same entity
+#[derive(Debug, Clone, Reflect)]
+#[reflect(Component, MapEntities)]
+pub struct TransformConstraint {
+    pub bones: Vec<Entity>,
+    pub target: Entity,
+    /// Copies the local transform of the target instead of its world transform
+    pub local: bool,
+    /// The target transform is added to the bones transforms instead of replacing them,
+    /// always starting from the bones animated pose of the current frame
+    pub relative: bool,
+}
+
+impl Default for TransformConstraint {
+    fn default() -> Self {
+        Self {
+            bones: vec![],
+            // TODO: Not a very good solution
+            target: Entity::new(u32::MAX),
+            local: false,
+            relative: false,
+        }
+    }
+}
+
+impl MapEntities for TransformConstraint {
+    fn map_entities(&mut self, entity_map: &EntityMap) -> Result<(), MapEntitiesError> {
+        for entity in self.bones.iter_mut() {
+            *entity = entity_map.get(*entity)?;
+        }
+        self.target = entity_map.get(self.target)?;
+
+        Ok(())
+    }
+}
+
+/// Rotation part of a [`TransformConstraint`]
+#[derive(Default, Debug, Clone, Copy, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct CopyRotationConstraint {
+    /// How much the rotation is copied, from 0 to 1
+    pub mix: f32,
+    /// Rotation added to the target rotation, in radians
+    pub offset: f32,
+}
+
+/// Translation part of a [`TransformConstraint`]
+#[derive(Default, Debug, Clone, Copy, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct CopyPositionConstraint {
+    /// How much the position is copied, from 0 to 1
+    pub mix: f32,
+    /// Offset in the target space
+    pub offset: Vec2,
+}
+
+/// Scale part of a [`TransformConstraint`]
+#[derive(Default, Debug, Clone, Copy, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct CopyScaleConstraint {
+    /// How much the scale is copied, from 0 to 1
+    pub mix: f32,
+    /// Scale added to the target scale
+    pub offset: Vec2,
+}
+
+/// Shear part of a [`TransformConstraint`], only the `y` axis is sheared
+#[derive(Default, Debug, Clone, Copy, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct CopyShearConstraint {
+    /// How much the shear is copied, from 0 to 1
+    pub mix: f32,
+    /// Shear added to the target shear, in radians
+    pub offset: f32,
+}
+
+impl TransformConstraint {
+    pub fn apply<M: WorldTransform>(
+        &self,
+        rotation: Option<&CopyRotationConstraint>,
+        position: Option<&CopyPositionConstraint>,
+        scale: Option<&CopyScaleConstraint>,
+        shear: Option<&CopyShearConstraint>,
+        bones: &mut BoneTransforms<M>,
+    ) {
+        let rotation = rotation.copied().unwrap_or_default();
+        let position = position.copied().unwrap_or_default();
+        let scale = scale.copied().unwrap_or_default();
+        let shear = shear.copied().unwrap_or_default();
+
+        if rotation.mix == 0.0 && position.mix == 0.0 && scale.mix == 0.0 && shear.mix == 0.0 {
+            return;
+        }
+
+        for bone in &self.bones {
+            let transform = if self.local {
+                let (target, transform) = match (bones.local(self.target), bones.local(*bone)) {
+                    (Some(target), Some(transform)) => (target, transform),
+                    _ => continue,
+                };
+
+                if self.relative {
+                    copy_relative_local(&transform, &target, &rotation, &position, &scale, &shear)
+                } else {
+                    copy_absolute_local(&transform, &target, &rotation, &position, &scale, &shear)
+                }
+            } else {
+                let (target, parent_world, world) = match (
+                    bones.world(self.target),
+                    bones.parent_world(*bone),
+                    bones.world(*bone),
+                ) {
+                    (Some(target), Some(parent_world), Some(world)) => {
+                        (target, parent_world, world)
+                    }
+                    _ => continue,
+                };
+
+                let world = if self.relative {
+                    copy_relative_world(&world, &target, &rotation, &position, &scale, &shear)
+                } else {
+                    copy_absolute_world(&world, &target, &rotation, &position, &scale, &shear)
+                };
+
+                transform_from_matrix(&(parent_world.inverse() * world))
+            };
+
+            bones.set_local(*bone, transform);
+        }
+    }
+}
+
+/// Wraps an angle in radians to the `[-PI, PI]` range
+#[inline]
+fn wrap_radians(angle: f32) -> f32 {
+    angle - (angle / (2.0 * PI)).round() * 2.0 * PI
+}
+
+/// Shear angle of the [`Transform2D`] `y` axis
+#[inline]
+fn shear_y_angle(transform: &Transform2D) -> f32 {
+    transform.shear.y.atan()
+}
+
+#[inline]
+fn rotate_axes(matrix: &mut Mat3, angle: f32) {
+    let rotation = Mat2::from_angle(angle);
+    matrix.x_axis = (rotation * matrix.x_axis.truncate()).extend(0.0);
+    matrix.y_axis = (rotation * matrix.y_axis.truncate()).extend(0.0);
+}
+
+/// Decomposes a 2D matrix in a [`Transform2D`], any shear is put in the `y` axis
+///
+/// **NOTE** Only exact for matrices without shear, see [`bone_shear`](crate::skeleton)
+pub(crate) fn transform_from_matrix(matrix: &Mat3) -> Transform2D {
+    let x_axis = matrix.x_axis.truncate();
+    let y_axis = matrix.y_axis.truncate();
+
+    let rotation = x_axis.y.atan2(x_axis.x);
+    let y_local = Mat2::from_angle(-rotation) * y_axis;
+
+    Transform2D {
+        translation: matrix.z_axis.truncate(),
+        rotation,
+        scale: Vec2::new(x_axis.length(), y_axis.length() * y_local.y.signum()),
+        shear: Vec2::new(0.0, (y_local.y.atan2(y_local.x) - FRAC_PI_2).tan()),
+    }
+}
+
+fn copy_absolute_world(
+    world: &Mat3,
+    target: &Mat3,
+    rotation: &CopyRotationConstraint,
+    position: &CopyPositionConstraint,
+    scale: &CopyScaleConstraint,
+    shear: &CopyShearConstraint,
+) -> Mat3 {
+    let mut world = *world;
+    let (ta, tc) = (target.x_axis.x, target.x_axis.y);
+    let (tb, td) = (target.y_axis.x, target.y_axis.y);
+    let reflect = if ta * td - tb * tc > 0.0 { 1.0 } else { -1.0 };
+
+    if rotation.mix != 0.0 {
+        let r = tc.atan2(ta) - world.x_axis.y.atan2(world.x_axis.x) + rotation.offset * reflect;
+        rotate_axes(&mut world, wrap_radians(r) * rotation.mix);
+    }
+
+    if position.mix != 0.0 {
+        let t = target.transform_point2(position.offset);
+        let p = world.z_axis.truncate();
+        world.z_axis = p.lerp(t, position.mix).extend(1.0);
+    }
+
+    if scale.mix > 0.0 {
+        let s = world.x_axis.truncate().length();
+        if s > 0.00001 {
+            let ts = Vec2::new(ta, tc).length();
+            world.x_axis *= (s + (ts - s + scale.offset.x) * scale.mix) / s;
+        }
+        let s = world.y_axis.truncate().length();
+        if s > 0.00001 {
+            let ts = Vec2::new(tb, td).length();
+            world.y_axis *= (s + (ts - s + scale.offset.y) * scale.mix) / s;
+        }
+    }
+
+    if shear.mix > 0.0 {
+        let (b, d) = (world.y_axis.x, world.y_axis.y);
+        let by = d.atan2(b);
+        let r = td.atan2(tb) - tc.atan2(ta) - (by - world.x_axis.y.atan2(world.x_axis.x));
+        let r = by + (wrap_radians(r) + shear.offset * reflect) * shear.mix;
+        let s = Vec2::new(b, d).length();
+        world.y_axis = Vec3::new(r.cos() * s, r.sin() * s, 0.0);
+    }
+
+    world
+}
+
+fn copy_relative_world(
+    world: &Mat3,
+    target: &Mat3,
+    rotation: &CopyRotationConstraint,
+    position: &CopyPositionConstraint,
+    scale: &CopyScaleConstraint,
+    shear: &CopyShearConstraint,
+) -> Mat3 {
+    let mut world = *world;
+    let (ta, tc) = (target.x_axis.x, target.x_axis.y);
+    let (tb, td) = (target.y_axis.x, target.y_axis.y);
+    let reflect = if ta * td - tb * tc > 0.0 { 1.0 } else { -1.0 };
+
+    if rotation.mix != 0.0 {
+        let r = tc.atan2(ta) + rotation.offset * reflect;
+        rotate_axes(&mut world, wrap_radians(r) * rotation.mix);
+    }
+
+    if position.mix != 0.0 {
+        let t = target.transform_point2(position.offset);
+        world.z_axis += (t * position.mix).extend(0.0);
+    }
+
+    if scale.mix > 0.0 {
+        world.x_axis *= (Vec2::new(ta, tc).length() - 1.0 + scale.offset.x) * scale.mix + 1.0;
+        world.y_axis *= (Vec2::new(tb, td).length() - 1.0 + scale.offset.y) * scale.mix + 1.0;
+    }
+
+    if shear.mix > 0.0 {
+        let r = wrap_radians(td.atan2(tb) - tc.atan2(ta));
+        let (b, d) = (world.y_axis.x, world.y_axis.y);
+        let r = d.atan2(b) + (r - FRAC_PI_2 + shear.offset * reflect) * shear.mix;
+        let s = Vec2::new(b, d).length();
+        world.y_axis = Vec3::new(r.cos() * s, r.sin() * s, 0.0);
+    }
+
+    world
+}
+
+fn copy_absolute_local(
+    transform: &Transform2D,
+    target: &Transform2D,
+    rotation: &CopyRotationConstraint,
+    position: &CopyPositionConstraint,
+    scale: &CopyScaleConstraint,
+    shear: &CopyShearConstraint,
+) -> Transform2D {
+    let mut transform = *transform;
+
+    if rotation.mix != 0.0 {
+        let r = wrap_radians(target.rotation - transform.rotation + rotation.offset);
+        transform.rotation += r * rotation.mix;
+    }
+
+    if position.mix != 0.0 {
+        transform.translation +=
+            (target.translation - transform.translation + position.offset) * position.mix;
+    }
+
+    if scale.mix != 0.0 {
+        transform.scale += (target.scale - transform.scale + scale.offset) * scale.mix;
+    }
+
+    if shear.mix != 0.0 {
+        let shear_y = shear_y_angle(&transform);
+        let r = wrap_radians(shear_y_angle(target) - shear_y + shear.offset);
+        transform.shear.y = (shear_y + r * shear.mix).tan();
+    }
+
+    transform
+}
+
+fn copy_relative_local(
+    transform: &Transform2D,
+    target: &Transform2D,
+    rotation: &CopyRotationConstraint,
+    position: &CopyPositionConstraint,
+    scale: &CopyScaleConstraint,
+    shear: &CopyShearConstraint,
+) -> Transform2D {
+    let mut transform = *transform;
+
+    if rotation.mix != 0.0 {
+        transform.rotation += (target.rotation + rotation.offset) * rotation.mix;
+    }
+
+    if position.mix != 0.0 {
+        transform.translation += (target.translation + position.offset) * position.mix;
+    }
+
+    if scale.mix != 0.0 {
+        transform.scale *= (target.scale - Vec2::ONE + scale.offset) * scale.mix + Vec2::ONE;
+    }
+
+    if shear.mix != 0.0 {
+        let shear_y =
+            shear_y_angle(&transform) + (shear_y_angle(target) + shear.offset) * shear.mix;
+        transform.shear.y = shear_y.tan();
+    }
+
+    transform
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transform::LocalToWorld2D;
+
+    #[test]
+    fn decompose_matrix() {
+        let transform = Transform2D {
+            translation: Vec2::new(3.0, -2.0),
+            rotation: 2.5,
+            scale: Vec2::new(2.0, 0.5),
+            shear: Vec2::ZERO,
+        };
+        let decomposed = transform_from_matrix(&transform.compute_matrix());
+        assert!((decomposed.translation - transform.translation).length() < 1e-5);
+        assert!((decomposed.rotation - transform.rotation).abs() < 1e-5);
+        assert!((decomposed.scale - transform.scale).length() < 1e-5);
+        assert!(decomposed.shear.length() < 1e-5);
+    }
+
+    #[test]
+    fn copy_world_transform() {
+        let mix = |mix| {
+            (
+                CopyRotationConstraint { mix, offset: 0.0 },
+                CopyPositionConstraint {
+                    mix,
+                    offset: Vec2::ZERO,
+                },
+                CopyScaleConstraint {
+                    mix,
+                    offset: Vec2::ZERO,
+                },
+                CopyShearConstraint { mix, offset: 0.0 },
+            )
+        };
+
+        let bone = Transform2D::from_xy(5.0, 1.0).compute_matrix();
+        let target = Transform2D {
+            translation: Vec2::new(-4.0, 7.0),
+            rotation: 1.2,
+            scale: Vec2::new(1.5, 3.0),
+            shear: Vec2::ZERO,
+        }
+        .compute_matrix();
+
+        let (rotation, position, scale, shear) = mix(1.0);
+        let world = copy_absolute_world(&bone, &target, &rotation, &position, &scale, &shear);
+        assert!(world.abs_diff_eq(target, 1e-4));
+
+        let (rotation, position, scale, shear) = mix(0.0);
+        let world = copy_absolute_world(&bone, &target, &rotation, &position, &scale, &shear);
+        assert!(world.abs_diff_eq(bone, 1e-4));
+
+        // Relative to an identity target keeps the bone in place
+        let (rotation, position, scale, shear) = mix(1.0);
+        let world =
+            copy_relative_world(&bone, &Mat3::IDENTITY, &rotation, &position, &scale, &shear);
+        assert!(world.abs_diff_eq(bone, 1e-4));
+    }
+
+    #[test]
+    fn copy_local_transform() {
+        let (rotation, position, scale, shear) = (
+            CopyRotationConstraint {
+                mix: 0.5,
+                offset: 0.0,
+            },
+            CopyPositionConstraint {
+                mix: 1.0,
+                offset: Vec2::new(1.0, 0.0),
+            },
+            CopyScaleConstraint {
+                mix: 1.0,
+                offset: Vec2::ZERO,
+            },
+            CopyShearConstraint::default(),
+        );
+
+        let bone = Transform2D::from_rotation(1.0);
+        let target = Transform2D {
+            translation: Vec2::new(2.0, 3.0),
+            rotation: 2.0,
+            scale: Vec2::new(2.0, 2.0),
+            shear: Vec2::ZERO,
+        };
+
+        let absolute = copy_absolute_local(&bone, &target, &rotation, &position, &scale, &shear);
+        assert!((absolute.rotation - 1.5).abs() < 1e-5);
+        assert_eq!(absolute.translation, Vec2::new(3.0, 3.0));
+        assert_eq!(absolute.scale, Vec2::new(2.0, 2.0));
+
+        let relative = copy_relative_local(&bone, &target, &rotation, &position, &scale, &shear);
+        assert!((relative.rotation - 2.0).abs() < 1e-5);
+        assert_eq!(relative.translation, Vec2::new(3.0, 3.0));
+        assert_eq!(relative.scale, Vec2::new(2.0, 2.0));
+    }
+
+    #[test]
+    fn relative_constraint_doesnt_accumulate() {
+        fn apply_constraints(
+            constraints: Query<(
+                &TransformConstraint,
+                Option<&CopyRotationConstraint>,
+                Option<&CopyPositionConstraint>,
+            )>,
+            mut bones: BoneTransforms<LocalToWorld2D>,
+        ) {
+            bones.reset();
+            for (constraint, rotation, position) in constraints.iter() {
+                constraint.apply(rotation, position, None, None, &mut bones);
+            }
+        }
+
+        for &local in &[true, false] {
+            let mut world = World::default();
+            let root = world
+                .spawn()
+                .insert(Transform2D::identity())
+                .insert(LocalToWorld2D::from(Transform2D::identity()))
+                .id();
+            let target_transform = Transform2D {
+                translation: Vec2::new(2.0, 0.0),
+                rotation: 1.0,
+                ..Transform2D::identity()
+            };
+            let target = world
+                .spawn()
+                .insert(target_transform)
+                .insert(LocalToWorld2D::from(target_transform))
+                .insert(Parent(root))
+                .id();
+            let bone = world
+                .spawn()
+                .insert(Transform2D::identity())
+                .insert(LocalToWorld2D::from(Transform2D::identity()))
+                .insert(Parent(root))
+                .id();
+            world
+                .entity_mut(root)
+                .insert(Children::with(&[target, bone]));
+            world
+                .spawn()
+                .insert(TransformConstraint {
+                    bones: vec![bone],
+                    target,
+                    local,
+                    relative: true,
+                })
+                .insert(CopyRotationConstraint {
+                    mix: 0.5,
+                    offset: 0.0,
+                })
+                .insert(CopyPositionConstraint {
+                    mix: 0.5,
+                    offset: Vec2::ZERO,
+                });
+
+            let mut stage = SystemStage::single_threaded();
+            stage.add_system(apply_constraints.system());
+
+            // The pose doesn't change between frames, neither does the constrained bone
+            stage.run(&mut world);
+            let first = world.get::<LocalToWorld2D>(bone).unwrap().0;
+            stage.run(&mut world);
+            let second = world.get::<LocalToWorld2D>(bone).unwrap().0;
+
+            assert!(!first.abs_diff_eq(Mat3::IDENTITY, 1e-4));
+            assert!(
+                first.abs_diff_eq(second, 1e-5),
+                "{:?} != {:?}",
+                first,
+                second
+            );
+            assert_eq!(
+                *world.get::<Transform2D>(bone).unwrap(),
+                Transform2D::identity()
+            );
+        }
+    }
+}
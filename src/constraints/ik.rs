@@ -0,0 +1,463 @@
+use std::f32::consts::PI;
+
+use bevy::{
+    ecs::{
+        entity::{EntityMap, MapEntities, MapEntitiesError},
+        reflect::ReflectMapEntities,
+    },
+    prelude::*,
+};
+
+use super::{BoneTransforms, WorldTransform};
+use crate::{skeleton::Skeleton, spine::Spine, transform::Transform2D};
+
+/// Rotates one or two bones so the tip of the last one reaches the `target` bone
+#[derive(Debug, Clone, Reflect)]
+#[reflect(Component, MapEntities)]
+pub struct IkConstraint {
+    /// Constrained bones, either a single bone or a parent and its child
+    pub bones: Vec<Entity>,
+    pub target: Entity,
+    /// How much the constraint is applied, from 0 to 1
+    pub mix: f32,
+    /// Two bones only, distance from the maximum reach of the bones where the rotation starts slowing down
+    pub softness: f32,
+    /// Two bones only, the bend direction
+    pub bend_positive: bool,
+    /// Single bone only, scales the bone down when the target is too close
+    pub compress: bool,
+    /// Scales the (parent) bone up when the target is out of reach
+    pub stretch: bool,
+    /// Single bone only, also scales the bone `y` axis when compressing or stretching
+    pub uniform: bool,
+}
+
+impl Default for IkConstraint {
+    fn default() -> Self {
+        Self {
+            bones: vec![],
+            // TODO: Not a very good solution
+            target: Entity::new(u32::MAX),
+            mix: 1.0,
+            softness: 0.0,
+            bend_positive: true,
+            compress: false,
+            stretch: false,
+            uniform: false,
+        }
+    }
+}
+
+impl MapEntities for IkConstraint {
+    fn map_entities(&mut self, entity_map: &EntityMap) -> Result<(), MapEntitiesError> {
+        for entity in self.bones.iter_mut() {
+            *entity = entity_map.get(*entity)?;
+        }
+        self.target = entity_map.get(self.target)?;
+
+        Ok(())
+    }
+}
+
+impl IkConstraint {
+    pub fn apply<M: WorldTransform>(
+        &self,
+        spine: &Spine,
+        skeleton: &Skeleton,
+        bones: &mut BoneTransforms<M>,
+    ) {
+        if self.mix <= 0.0 {
+            return;
+        }
+
+        let target = if let Some(target) = bones.world(self.target) {
+            target.z_axis.truncate()
+        } else {
+            return;
+        };
+
+        // Bones lengths aren't stored in the entities
+        let length = |entity: &Entity| {
+            skeleton
+                .bones
+                .iter()
+                .position(|bone| bone == entity)
+                .map_or(0.0, |index| spine.bones[index].length)
+        };
+
+        match self.bones.as_slice() {
+            [bone] => {
+                let (parent_world, transform) =
+                    match (bones.parent_world(*bone), bones.local(*bone)) {
+                        (Some(parent_world), Some(transform)) => (parent_world, transform),
+                        _ => return,
+                    };
+
+                let transform = solve_one_bone(
+                    &parent_world,
+                    &transform,
+                    length(bone),
+                    target,
+                    self.compress,
+                    self.stretch,
+                    self.uniform,
+                    self.mix,
+                );
+                bones.set_local(*bone, transform);
+            }
+            [parent, child] => {
+                let (grandparent_world, parent_transform, child_transform) = match (
+                    bones.parent_world(*parent),
+                    bones.local(*parent),
+                    bones.local(*child),
+                ) {
+                    (Some(grandparent_world), Some(parent_transform), Some(child_transform)) => {
+                        (grandparent_world, parent_transform, child_transform)
+                    }
+                    _ => return,
+                };
+
+                let (parent_transform, child_transform) = solve_two_bones(
+                    &grandparent_world,
+                    &parent_transform,
+                    length(parent),
+                    &child_transform,
+                    length(child),
+                    target,
+                    if self.bend_positive { 1.0 } else { -1.0 },
+                    self.stretch,
+                    self.softness,
+                    self.mix,
+                );
+                bones.set_local(*parent, parent_transform);
+                bones.set_local(*child, child_transform);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Wraps an angle in radians to the `[-PI, PI]` range
+#[inline]
+fn wrap_radians(angle: f32) -> f32 {
+    angle - (angle / (2.0 * PI)).round() * 2.0 * PI
+}
+
+/// Angle of the bone `x` axis relative to its rotation, given by the shear
+#[inline]
+fn shear_angle(transform: &Transform2D) -> f32 {
+    let x_axis = transform.compute_matrix().x_axis;
+    let mut angle = x_axis.y.atan2(x_axis.x) - transform.rotation;
+    if transform.scale.x < 0.0 {
+        angle += PI;
+    }
+    wrap_radians(angle)
+}
+
+/// Points the bone `x` axis towards the `target` (in world space), returns the bone new local transform
+#[allow(clippy::too_many_arguments)]
+pub fn solve_one_bone(
+    parent_world: &Mat3,
+    transform: &Transform2D,
+    length: f32,
+    target: Vec2,
+    compress: bool,
+    stretch: bool,
+    uniform: bool,
+    alpha: f32,
+) -> Transform2D {
+    // Target in the parent space, relative to the bone
+    let t = parent_world.inverse().transform_point2(target) - transform.translation;
+
+    let mut rotation = t.y.atan2(t.x) - shear_angle(transform) - transform.rotation;
+    if transform.scale.x < 0.0 {
+        rotation += PI;
+    }
+    let rotation = wrap_radians(rotation);
+
+    let mut scale = transform.scale;
+    if compress || stretch {
+        let b = length * scale.x;
+        let d = t.length();
+        if ((compress && d < b) || (stretch && d > b)) && b > 0.0001 {
+            let s = (d / b - 1.0) * alpha + 1.0;
+            scale.x *= s;
+            if uniform {
+                scale.y *= s;
+            }
+        }
+    }
+
+    Transform2D {
+        rotation: transform.rotation + rotation * alpha,
+        scale,
+        ..*transform
+    }
+}
+
+/// Bends the `parent` and `child` bones so the tip of the child reaches the `target` (in world space),
+/// returns the new local transforms of both bones
+#[allow(clippy::too_many_arguments)]
+pub fn solve_two_bones(
+    grandparent_world: &Mat3,
+    parent: &Transform2D,
+    parent_length: f32,
+    child: &Transform2D,
+    child_length: f32,
+    target: Vec2,
+    bend_direction: f32,
+    stretch: bool,
+    softness: f32,
+    alpha: f32,
+) -> (Transform2D, Transform2D) {
+    let p = parent.translation;
+    let mut psx = parent.scale.x;
+    let mut psy = parent.scale.y;
+    let mut csx = child.scale.x;
+    let mut sx = psx;
+
+    // Flipped bones
+    let (os1, mut s2) = if psx < 0.0 {
+        psx = -psx;
+        (PI, -1.0)
+    } else {
+        (0.0, 1.0)
+    };
+    if psy < 0.0 {
+        psy = -psy;
+        s2 = -s2;
+    }
+    let os2 = if csx < 0.0 {
+        csx = -csx;
+        PI
+    } else {
+        0.0
+    };
+
+    let parent_world = *grandparent_world * parent.compute_matrix();
+    let grandparent_inv = grandparent_world.inverse();
+
+    // Non uniform scaled parents ignore the child `y` offset
+    let uniform = (psx - psy).abs() <= 0.0001;
+    let c = Vec2::new(
+        child.translation.x,
+        if uniform { child.translation.y } else { 0.0 },
+    );
+
+    // Child position in the grandparent space, relative to the parent
+    let d = grandparent_inv.transform_point2(parent_world.transform_point2(c)) - p;
+    let l1 = d.length();
+    let mut l2 = child_length * csx;
+    if l1 < 0.0001 {
+        let parent = solve_one_bone(
+            grandparent_world,
+            parent,
+            parent_length,
+            target,
+            false,
+            stretch,
+            false,
+            alpha,
+        );
+        let child = Transform2D {
+            translation: c,
+            rotation: 0.0,
+            ..*child
+        };
+        return (parent, child);
+    }
+
+    // Target in the grandparent space, relative to the parent
+    let mut t = grandparent_inv.transform_point2(target) - p;
+    let mut dd = t.length_squared();
+    if softness != 0.0 {
+        let softness = softness * psx * (csx + 1.0) * 0.5;
+        let td = dd.sqrt();
+        let sd = td - l1 - l2 * psx + softness;
+        if sd > 0.0 {
+            let p = (sd / (softness * 2.0)).min(1.0) - 1.0;
+            let p = (sd - softness * (1.0 - p * p)) / td;
+            t -= t * p;
+            dd = t.length_squared();
+        }
+    }
+
+    let (a1, a2) = if uniform {
+        l2 *= psx;
+        let mut cos = (dd - l1 * l1 - l2 * l2) / (2.0 * l1 * l2);
+        if cos < -1.0 {
+            cos = -1.0;
+        } else if cos > 1.0 {
+            cos = 1.0;
+            if stretch {
+                sx *= (dd.sqrt() / (l1 + l2) - 1.0) * alpha + 1.0;
+            }
+        }
+        let a2 = cos.acos() * bend_direction;
+        let a = l1 + l2 * cos;
+        let b = l2 * a2.sin();
+        let a1 = (t.y * a - t.x * b).atan2(t.x * a + t.y * b);
+        (a1, a2)
+    } else {
+        solve_non_uniform(l1, l2, psx, psy, t, dd, bend_direction)
+    };
+
+    let os = c.y.atan2(c.x) * s2;
+
+    let a1 = wrap_radians(a1 - os + os1 - parent.rotation);
+    let parent = Transform2D {
+        translation: p,
+        rotation: parent.rotation + a1 * alpha,
+        scale: Vec2::new(sx, parent.scale.y),
+        shear: Vec2::ZERO,
+    };
+
+    let a2 = wrap_radians((a2 + os - shear_angle(child)) * s2 + os2 - child.rotation);
+    let child = Transform2D {
+        translation: c,
+        rotation: child.rotation + a2 * alpha,
+        ..*child
+    };
+
+    (parent, child)
+}
+
+/// Two bones solution when the parent has a non uniform scale,
+/// the child tip moves along an ellipse
+fn solve_non_uniform(
+    l1: f32,
+    l2: f32,
+    psx: f32,
+    psy: f32,
+    t: Vec2,
+    dd: f32,
+    bend_direction: f32,
+) -> (f32, f32) {
+    let a = psx * l2;
+    let b = psy * l2;
+    let aa = a * a;
+    let bb = b * b;
+    let ta = t.y.atan2(t.x);
+
+    let c = bb * l1 * l1 + aa * dd - aa * bb;
+    let c1 = -2.0 * bb * l1;
+    let c2 = bb - aa;
+    let d = c1 * c1 - 4.0 * c2 * c;
+    if d >= 0.0 {
+        let mut q = d.sqrt();
+        if c1 < 0.0 {
+            q = -q;
+        }
+        q = -(c1 + q) * 0.5;
+        let r0 = q / c2;
+        let r1 = c / q;
+        let r = if r0.abs() < r1.abs() { r0 } else { r1 };
+        if r * r <= dd {
+            let y = (dd - r * r).sqrt() * bend_direction;
+            let a1 = ta - y.atan2(r);
+            let a2 = (y / psy).atan2((r - l1) / psx);
+            return (a1, a2);
+        }
+    }
+
+    // Target out of reach, pick the closest or the farthest point of the ellipse
+    let mut min_angle = PI;
+    let mut min_x = l1 - a;
+    let mut min_dist = min_x * min_x;
+    let mut min_y = 0.0;
+    let mut max_angle = 0.0;
+    let mut max_x = l1 + a;
+    let mut max_dist = max_x * max_x;
+    let mut max_y = 0.0;
+
+    let c = -a * l1 / (aa - bb);
+    if (-1.0..=1.0).contains(&c) {
+        let c = c.acos();
+        let x = a * c.cos() + l1;
+        let y = b * c.sin();
+        let d = x * x + y * y;
+        if d < min_dist {
+            min_angle = c;
+            min_dist = d;
+            min_x = x;
+            min_y = y;
+        }
+        if d > max_dist {
+            max_angle = c;
+            max_dist = d;
+            max_x = x;
+            max_y = y;
+        }
+    }
+
+    if dd <= (min_dist + max_dist) * 0.5 {
+        (
+            ta - (min_y * bend_direction).atan2(min_x),
+            min_angle * bend_direction,
+        )
+    } else {
+        (
+            ta - (max_y * bend_direction).atan2(max_x),
+            max_angle * bend_direction,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bone_tip(world: &Mat3, transform: &Transform2D, length: f32) -> (Mat3, Vec2) {
+        let world = *world * transform.compute_matrix();
+        (world, world.transform_point2(Vec2::new(length, 0.0)))
+    }
+
+    #[test]
+    fn one_bone_reaches_target() {
+        let parent = Mat3::from_scale_angle_translation(Vec2::ONE, 0.5, Vec2::new(10.0, 5.0));
+        let bone = Transform2D {
+            translation: Vec2::new(3.0, 1.0),
+            rotation: 1.0,
+            ..Default::default()
+        };
+        let target = Vec2::new(-20.0, 30.0);
+
+        let solved = solve_one_bone(&parent, &bone, 10.0, target, false, false, false, 1.0);
+        let (world, tip) = bone_tip(&parent, &solved, 10.0);
+        let origin = world.z_axis.truncate();
+        let direction = (tip - origin).normalize();
+        assert!((direction - (target - origin).normalize()).length() < 1e-4);
+
+        // Stretches to reach the target
+        let solved = solve_one_bone(&parent, &bone, 10.0, target, false, true, false, 1.0);
+        let (_, tip) = bone_tip(&parent, &solved, 10.0);
+        assert!((tip - target).length() < 1e-3);
+    }
+
+    #[test]
+    fn two_bones_reach_target() {
+        let grandparent = Mat3::IDENTITY;
+        let parent = Transform2D::default();
+        let child = Transform2D::from_xy(10.0, 0.0);
+        let target = Vec2::new(5.0, 12.0);
+
+        for &bend in &[1.0, -1.0] {
+            let (p, c) = solve_two_bones(
+                &grandparent,
+                &parent,
+                10.0,
+                &child,
+                10.0,
+                target,
+                bend,
+                false,
+                0.0,
+                1.0,
+            );
+            let (parent_world, _) = bone_tip(&grandparent, &p, 10.0);
+            let (_, tip) = bone_tip(&parent_world, &c, 10.0);
+            assert!((tip - target).length() < 1e-3);
+            assert!(c.rotation * bend > 0.0);
+        }
+    }
+}
@@ -1,13 +1,229 @@
-//! Stub
+//! Skeleton constraints, they are applied after the transform propagation by the [`constraints_system`]
+//! in the order given by [`Skeleton::constraints`]; Constrained bones have the world matrices of their
+//! hierarchy updated right away, so the next constraint sees the final result of the previous ones.
+//!
+//! **NOTE** Constraints never change the bones [`Transform2D`], which keeps the pose given by the
+//! animations (or the setup pose), every frame they start over from this pose
+
+use bevy::{
+    ecs::{component::Component, system::SystemParam},
+    prelude::*,
+    utils::HashMap,
+};
+
+use crate::{
+    attachment::PathAttachment,
+    skeleton::Skeleton,
+    spine::Spine,
+    transform::{LocalToWorld, LocalToWorld2D, Transform2D, Transform2D5System, TransformMode},
+};
+
+mod copy;
+mod ik;
+mod path;
+
+pub use copy::*;
+pub use ik::*;
+pub use path::*;
 
 pub struct LookAtConstraint;
 
-pub struct CopyPositionConstraint;
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+pub enum ConstraintSystem {
+    Apply,
+}
+
+/// World matrix of the bones, either [`LocalToWorld2D`] or [`LocalToWorld`]
+pub trait WorldTransform: Component + Copy {
+    /// 2D part of the world matrix
+    fn matrix_2d(&self) -> Mat3;
+
+    /// World matrix of a child with the given local `transform`
+    fn mul_transform_2d(&self, transform: Transform2D) -> Self;
+}
+
+impl WorldTransform for LocalToWorld2D {
+    #[inline]
+    fn matrix_2d(&self) -> Mat3 {
+        self.0
+    }
+
+    #[inline]
+    fn mul_transform_2d(&self, transform: Transform2D) -> Self {
+        self.mul_transform(transform)
+    }
+}
+
+impl WorldTransform for LocalToWorld {
+    #[inline]
+    fn matrix_2d(&self) -> Mat3 {
+        LocalToWorld2D::from(*self).0
+    }
+
+    #[inline]
+    fn mul_transform_2d(&self, transform: Transform2D) -> Self {
+        *self * LocalToWorld::from(LocalToWorld2D::from(transform))
+    }
+}
+
+/// Access to the bones local and world transforms
+#[derive(SystemParam)]
+pub struct BoneTransforms<'a, M: WorldTransform> {
+    transforms: Query<'a, &'static Transform2D>,
+    worlds: Query<'a, &'static mut M>,
+    parents: Query<'a, &'static Parent>,
+    children: Query<'a, &'static Children>,
+    /// Local transforms of the constrained bones, kept apart from their animated [`Transform2D`]
+    constrained: Local<'a, HashMap<Entity, Transform2D>>,
+}
+
+impl<'a, M: WorldTransform> BoneTransforms<'a, M> {
+    /// Restores the world matrices of the bones constrained in the last frame, constraints
+    /// must start from the animated pose otherwise partial mixes would accumulate every frame
+    pub fn reset(&mut self) {
+        let constrained = self
+            .constrained
+            .drain()
+            .map(|(bone, _)| bone)
+            .collect::<Vec<_>>();
+        for bone in constrained {
+            if let Some(parent) = self.parent_world_transform(bone) {
+                self.propagate(&parent, bone);
+            }
+        }
+    }
+
+    /// Local transform of the bone, either the constrained or the animated one
+    pub fn local(&self, bone: Entity) -> Option<Transform2D> {
+        self.constrained.get(&bone).copied().or_else(|| {
+            self.transforms
+                .get_component::<Transform2D>(bone)
+                .ok()
+                .copied()
+        })
+    }
+
+    /// 2D world matrix of the bone
+    pub fn world(&self, bone: Entity) -> Option<Mat3> {
+        self.worlds
+            .get_component::<M>(bone)
+            .ok()
+            .map(WorldTransform::matrix_2d)
+    }
+
+    /// 2D world matrix of the bone parent
+    pub fn parent_world(&self, bone: Entity) -> Option<Mat3> {
+        let parent = self.parents.get(bone).ok()?;
+        self.world(parent.0)
+    }
+
+    /// Changes the bone local transform for this frame, updating the world matrix of the bone
+    /// and its descendants
+    pub fn set_local(&mut self, bone: Entity, transform: Transform2D) {
+        let parent = if let Some(parent) = self.parent_world_transform(bone) {
+            parent
+        } else {
+            return;
+        };
+
+        if self.transforms.get(bone).is_ok() {
+            self.constrained.insert(bone, transform);
+        }
+
+        self.propagate(&parent, bone);
+    }
+
+    fn parent_world_transform(&self, bone: Entity) -> Option<M> {
+        let parent = self.parents.get(bone).ok()?;
+        self.worlds.get_component::<M>(parent.0).ok().copied()
+    }
+
+    fn propagate(&mut self, parent: &M, entity: Entity) {
+        let world = if let Some(transform) = self.local(entity) {
+            parent.mul_transform_2d(transform)
+        } else {
+            return;
+        };
+
+        if let Ok(mut local_to_world) = self.worlds.get_mut(entity) {
+            *local_to_world = world;
+        } else {
+            return;
+        }
+
+        if let Ok(children) = self.children.get(entity) {
+            // TODO: Avoid allocating
+            for child in children.iter().copied().collect::<Vec<_>>() {
+                self.propagate(&world, child);
+            }
+        }
+    }
+}
+
+/// Applies every skeleton constraint
+pub fn constraints_system<M: WorldTransform>(
+    spines: Res<Assets<Spine>>,
+    skeletons: Query<&Skeleton>,
+    ik_constraints: Query<&IkConstraint>,
+    transform_constraints: Query<(
+        &TransformConstraint,
+        Option<&CopyRotationConstraint>,
+        Option<&CopyPositionConstraint>,
+        Option<&CopyScaleConstraint>,
+        Option<&CopyShearConstraint>,
+    )>,
+    path_constraints: Query<&FollowPathConstraint>,
+    paths: Query<&PathAttachment>,
+    mut bones: BoneTransforms<M>,
+) {
+    bones.reset();
+
+    for skeleton in skeletons.iter() {
+        let spine = if let Some(spine) = spines.get(&skeleton.spine) {
+            spine
+        } else {
+            continue;
+        };
 
-pub struct CopyRotationConstraint;
+        for entity in &skeleton.constraints {
+            if let Ok(ik) = ik_constraints.get(*entity) {
+                ik.apply(spine, skeleton, &mut bones);
+            } else if let Ok((transform, rotation, position, scale, shear)) =
+                transform_constraints.get(*entity)
+            {
+                transform.apply(rotation, position, scale, shear, &mut bones);
+            } else if let Ok(path_constraint) = path_constraints.get(*entity) {
+                if let Ok(path) = paths.get(path_constraint.target) {
+                    path_constraint.apply(spine, skeleton, path, &mut bones);
+                }
+            }
+        }
+    }
+}
 
-pub struct CopyScaleConstraint;
+/// Adds the [`constraints_system`] matching the [`TransformMode`], runs in the [`CoreStage::PostUpdate`]
+/// after the transform propagation
+#[derive(Default)]
+pub struct SpineConstraintPlugin {
+    pub transform_mode: TransformMode,
+}
 
-pub struct FollowPathConstraint;
+impl Plugin for SpineConstraintPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        match self.transform_mode {
+            TransformMode::Transform2D => add_constraints_system::<LocalToWorld2D>(app),
+            TransformMode::Transform2D5 => add_constraints_system::<LocalToWorld>(app),
+        }
+    }
+}
 
-pub struct IKConstraint;
+fn add_constraints_system<M: WorldTransform>(app: &mut AppBuilder) {
+    app.add_system_to_stage(
+        CoreStage::PostUpdate,
+        constraints_system::<M>
+            .system()
+            .label(ConstraintSystem::Apply)
+            .after(Transform2D5System::PropagateTransform2D)
+            .before(Transform2D5System::ChildOfTransform2DPropagate),
+    );
+}
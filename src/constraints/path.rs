@@ -0,0 +1,597 @@
+use std::f32::consts::PI;
+
+use bevy::{
+    ecs::{
+        entity::{EntityMap, MapEntities, MapEntitiesError},
+        reflect::ReflectMapEntities,
+    },
+    prelude::*,
+};
+
+use super::{copy::transform_from_matrix, BoneTransforms, WorldTransform};
+use crate::{
+    attachment::PathAttachment,
+    skeleton::Skeleton,
+    spine::{
+        spine::{PositionMode, RotateMode, SpacingMode},
+        Spine,
+    },
+};
+
+const EPSILON: f32 = 0.00001;
+
+/// Moves and rotates the `bones` along the [`PathAttachment`] of the `target` slot
+#[derive(Debug, Clone, Reflect)]
+#[reflect(Component, MapEntities)]
+pub struct FollowPathConstraint {
+    pub bones: Vec<Entity>,
+    /// Slot entity with the [`PathAttachment`]
+    pub target: Entity,
+    pub position_mode: PositionMode,
+    pub spacing_mode: SpacingMode,
+    pub rotate_mode: RotateMode,
+    /// Rotation added to the bones, in radians
+    pub offset_rotation: f32,
+    /// Position of the first bone along the path, either in world units or a percentage of the path length
+    pub position: f32,
+    /// Distance between each bone, its meaning is given by the `spacing_mode`
+    pub spacing: f32,
+    /// How much the rotation is applied, from 0 to 1
+    pub rotate_mix: f32,
+    /// How much the translation is applied, from 0 to 1
+    pub translate_mix: f32,
+}
+
+impl Default for FollowPathConstraint {
+    fn default() -> Self {
+        Self {
+            bones: vec![],
+            // TODO: Not a very good solution
+            target: Entity::new(u32::MAX),
+            position_mode: PositionMode::default(),
+            spacing_mode: SpacingMode::default(),
+            rotate_mode: RotateMode::default(),
+            offset_rotation: 0.0,
+            position: 0.0,
+            spacing: 0.0,
+            rotate_mix: 1.0,
+            translate_mix: 1.0,
+        }
+    }
+}
+
+impl MapEntities for FollowPathConstraint {
+    fn map_entities(&mut self, entity_map: &EntityMap) -> Result<(), MapEntitiesError> {
+        for entity in self.bones.iter_mut() {
+            *entity = entity_map.get(*entity)?;
+        }
+        self.target = entity_map.get(self.target)?;
+
+        Ok(())
+    }
+}
+
+impl FollowPathConstraint {
+    pub fn apply<M: WorldTransform>(
+        &self,
+        spine: &Spine,
+        skeleton: &Skeleton,
+        path: &PathAttachment,
+        bones: &mut BoneTransforms<M>,
+    ) {
+        if self.rotate_mix <= 0.0 && self.translate_mix <= 0.0 {
+            return;
+        }
+
+        let target = if let Some(target) = bones.world(self.target) {
+            target
+        } else {
+            return;
+        };
+
+        // TODO: Avoid allocating
+        let mut path_world = vec![];
+        path.vertices
+            .compute_world(&target, &[], |bone| bones.world(bone), &mut path_world);
+
+        // Bones lengths aren't stored in the entities
+        let mut bones_world = Vec::with_capacity(self.bones.len());
+        for bone in &self.bones {
+            let world = if let Some(world) = bones.world(*bone) {
+                world
+            } else {
+                return;
+            };
+
+            let length = skeleton
+                .bones
+                .iter()
+                .position(|entity| entity == bone)
+                .map_or(0.0, |index| spine.bones[index].length);
+
+            bones_world.push((world, length));
+        }
+
+        let worlds = follow_path(self, path, &path_world, &target, &bones_world);
+
+        // Each bone is set in order, so its parent (usually the previous bone) already have its final world matrix
+        for (bone, world) in self.bones.iter().zip(worlds.iter()) {
+            if let Some(parent_world) = bones.parent_world(*bone) {
+                bones.set_local(
+                    *bone,
+                    transform_from_matrix(&(parent_world.inverse() * *world)),
+                );
+            }
+        }
+    }
+}
+
+/// Computes the new world matrices of the constrained bones, given by their current world matrix and length;
+/// `path_world` are the path vertices in world space and `target` the world matrix of the path slot
+pub fn follow_path(
+    constraint: &FollowPathConstraint,
+    path: &PathAttachment,
+    path_world: &[Vec2],
+    target: &Mat3,
+    bones: &[(Mat3, f32)],
+) -> Vec<Mat3> {
+    let mut worlds: Vec<Mat3> = bones.iter().map(|(world, _)| *world).collect();
+    let min_vertices = if path.closed { 3 } else { 6 };
+    if bones.is_empty()
+        || path_world.len() < min_vertices
+        || path.lengths.len() < path_world.len() / 3
+    {
+        return worlds;
+    }
+
+    let rotate = constraint.rotate_mix > 0.0;
+    let percent_spacing = matches!(constraint.spacing_mode, SpacingMode::Percent);
+    let tangents = matches!(constraint.rotate_mode, RotateMode::Tangent);
+    let scale = matches!(constraint.rotate_mode, RotateMode::ChainScale);
+    let bone_count = bones.len();
+    let spaces_count = if tangents { bone_count } else { bone_count + 1 };
+
+    let mut spaces = vec![0.0; spaces_count];
+    let mut lengths = vec![0.0; bone_count];
+    if scale || !percent_spacing {
+        let length_spacing = matches!(constraint.spacing_mode, SpacingMode::Length);
+        for (i, (world, setup_length)) in bones.iter().take(spaces_count - 1).enumerate() {
+            let length = (world.x_axis.truncate() * *setup_length).length();
+            if *setup_length < EPSILON {
+                spaces[i + 1] = 0.0;
+            } else if percent_spacing {
+                lengths[i] = length;
+                spaces[i + 1] = constraint.spacing;
+            } else {
+                lengths[i] = length;
+                let spacing = if length_spacing {
+                    setup_length + constraint.spacing
+                } else {
+                    constraint.spacing
+                };
+                spaces[i + 1] = spacing * length / setup_length;
+            }
+        }
+    } else {
+        for space in spaces.iter_mut().skip(1) {
+            *space = constraint.spacing;
+        }
+    }
+
+    let positions = compute_world_positions(
+        path,
+        path_world,
+        &mut spaces,
+        constraint.position,
+        tangents,
+        matches!(constraint.position_mode, PositionMode::Percent),
+        percent_spacing,
+    );
+
+    let mut bone_position = positions[0].truncate();
+    let tip;
+    let mut offset_rotation = constraint.offset_rotation;
+    if offset_rotation == 0.0 {
+        tip = matches!(constraint.rotate_mode, RotateMode::Chain);
+    } else {
+        tip = false;
+        if target.determinant() < 0.0 {
+            offset_rotation = -offset_rotation;
+        }
+    }
+
+    for (i, world) in worlds.iter_mut().enumerate() {
+        let translation = world.z_axis.truncate();
+        let translation = translation + (bone_position - translation) * constraint.translate_mix;
+        world.z_axis = translation.extend(1.0);
+
+        let next = positions.get(i + 1).copied().unwrap_or(Vec3::ZERO);
+        let delta = next.truncate() - bone_position;
+        if scale && lengths[i] != 0.0 {
+            let s = (delta.length() / lengths[i] - 1.0) * constraint.rotate_mix + 1.0;
+            world.x_axis *= s;
+        }
+
+        bone_position = next.truncate();
+
+        if rotate {
+            let x_axis = world.x_axis.truncate();
+            let mut r = if tangents {
+                positions[i].z
+            } else if spaces[i + 1] == 0.0 {
+                next.z
+            } else {
+                delta.y.atan2(delta.x)
+            };
+            r -= x_axis.y.atan2(x_axis.x);
+
+            if tip {
+                let length = bones[i].1;
+                let bone_tip = Mat2::from_angle(r) * x_axis * length;
+                bone_position += (bone_tip - delta) * constraint.rotate_mix;
+            } else {
+                r += offset_rotation;
+            }
+
+            if r > PI {
+                r -= 2.0 * PI;
+            } else if r < -PI {
+                r += 2.0 * PI;
+            }
+
+            let rotation = Mat2::from_angle(r * constraint.rotate_mix);
+            world.x_axis = (rotation * world.x_axis.truncate()).extend(0.0);
+            world.y_axis = (rotation * world.y_axis.truncate()).extend(0.0);
+        }
+    }
+
+    worlds
+}
+
+/// Positions along the path for each space, the `z` component holds the path tangent angle
+fn compute_world_positions(
+    path: &PathAttachment,
+    world: &[Vec2],
+    spaces: &mut [f32],
+    mut position: f32,
+    tangents: bool,
+    percent_position: bool,
+    percent_spacing: bool,
+) -> Vec<Vec3> {
+    let spaces_count = spaces.len();
+    let mut out = vec![Vec3::ZERO; spaces_count];
+    let closed = path.closed;
+    let vertex_count = world.len();
+    let mut curve_count = vertex_count / 3;
+    let mut prev_curve = None;
+
+    if !path.constant_speed {
+        let lengths = &path.lengths;
+        curve_count -= if closed { 1 } else { 2 };
+        let path_length = lengths[curve_count];
+        if percent_position {
+            position *= path_length;
+        }
+        if percent_spacing {
+            for space in spaces.iter_mut().skip(1) {
+                *space *= path_length;
+            }
+        }
+
+        let mut curve = 0;
+        for (i, out) in out.iter_mut().enumerate() {
+            let space = spaces[i];
+            position += space;
+            let mut p = position;
+
+            if closed {
+                p %= path_length;
+                if p < 0.0 {
+                    p += path_length;
+                }
+                curve = 0;
+            } else if p < 0.0 {
+                *out = before_position(p, world[1], world[2]);
+                continue;
+            } else if p > path_length {
+                *out = after_position(
+                    p - path_length,
+                    world[vertex_count - 3],
+                    world[vertex_count - 2],
+                );
+                continue;
+            }
+
+            // Curve containing the position
+            while curve < curve_count && p > lengths[curve] {
+                curve += 1;
+            }
+            if curve == 0 {
+                p /= lengths[0];
+            } else {
+                let prev = lengths[curve - 1];
+                p = (p - prev) / (lengths[curve] - prev);
+            }
+
+            let (p1, c1, c2, p2) = if closed && curve == curve_count {
+                (
+                    world[vertex_count - 2],
+                    world[vertex_count - 1],
+                    world[0],
+                    world[1],
+                )
+            } else {
+                let v = curve * 3 + 1;
+                (world[v], world[v + 1], world[v + 2], world[v + 3])
+            };
+
+            *out = curve_position(p, p1, c1, c2, p2, tangents || (i > 0 && space == 0.0));
+        }
+
+        return out;
+    }
+
+    // Path without the first control point (and the last one when open),
+    // closed paths wrap around to the first curve point
+    let mut points: Vec<Vec2> = if closed {
+        world[1..]
+            .iter()
+            .chain(world[..2].iter())
+            .copied()
+            .collect()
+    } else {
+        curve_count -= 1;
+        world[1..vertex_count - 1].to_vec()
+    };
+    if points.len() < curve_count * 3 + 1 {
+        points.resize(curve_count * 3 + 1, Vec2::ZERO);
+    }
+
+    // Curves lengths
+    let mut curves = vec![0.0; curve_count];
+    let mut path_length = 0.0;
+    for (i, curve) in curves.iter_mut().enumerate() {
+        let v = i * 3;
+        path_length += bezier_length(points[v], points[v + 1], points[v + 2], points[v + 3]);
+        *curve = path_length;
+    }
+
+    if percent_position {
+        position *= path_length;
+    } else {
+        position *= path_length / path.lengths[curve_count - 1];
+    }
+    if percent_spacing {
+        for space in spaces.iter_mut().skip(1) {
+            *space *= path_length;
+        }
+    }
+
+    let mut segments = [0.0; 10];
+    let mut curve_length = 0.0;
+    let mut curve = 0;
+    let mut segment = 0;
+    let (mut p1, mut c1, mut c2, mut p2) = (Vec2::ZERO, Vec2::ZERO, Vec2::ZERO, Vec2::ZERO);
+    for (i, out) in out.iter_mut().enumerate() {
+        let space = spaces[i];
+        position += space;
+        let mut p = position;
+
+        if closed {
+            p %= path_length;
+            if p < 0.0 {
+                p += path_length;
+            }
+            curve = 0;
+        } else if p < 0.0 {
+            *out = before_position(p, points[0], points[1]);
+            continue;
+        } else if p > path_length {
+            let last = points.len() - 2;
+            *out = after_position(p - path_length, points[last], points[last + 1]);
+            continue;
+        }
+
+        // Curve containing the position
+        while curve < curve_count - 1 && p > curves[curve] {
+            curve += 1;
+        }
+        if curve == 0 {
+            p /= curves[0];
+        } else {
+            let prev = curves[curve - 1];
+            p = (p - prev) / (curves[curve] - prev);
+        }
+
+        // Curve segments lengths
+        if prev_curve != Some(curve) {
+            prev_curve = Some(curve);
+            let v = curve * 3;
+            p1 = points[v];
+            c1 = points[v + 1];
+            c2 = points[v + 2];
+            p2 = points[v + 3];
+            curve_length = bezier_segments(p1, c1, c2, p2, &mut segments);
+            segment = 0;
+        }
+
+        // Weight by segment length
+        p *= curve_length;
+        while segment < segments.len() - 1 && p > segments[segment] {
+            segment += 1;
+        }
+        if segment == 0 {
+            p /= segments[0];
+        } else {
+            let prev = segments[segment - 1];
+            p = segment as f32 + (p - prev) / (segments[segment] - prev);
+        }
+
+        *out = curve_position(p * 0.1, p1, c1, c2, p2, tangents || (i > 0 && space == 0.0));
+    }
+
+    out
+}
+
+/// Position before the path start, along the line of the first curve point and its outgoing control point
+fn before_position(p: f32, p1: Vec2, p2: Vec2) -> Vec3 {
+    let delta = p2 - p1;
+    let r = delta.y.atan2(delta.x);
+    (p1 + Vec2::new(r.cos(), r.sin()) * p).extend(r)
+}
+
+/// Position after the path end, along the line of the last incoming control point and the last curve point
+fn after_position(p: f32, p1: Vec2, p2: Vec2) -> Vec3 {
+    let delta = p2 - p1;
+    let r = delta.y.atan2(delta.x);
+    (p2 + Vec2::new(r.cos(), r.sin()) * p).extend(r)
+}
+
+/// Point of the bezier curve at `p` (from 0 to 1), the tangent angle is only computed when `tangents` is set
+fn curve_position(p: f32, p1: Vec2, c1: Vec2, c2: Vec2, p2: Vec2, tangents: bool) -> Vec3 {
+    if p < EPSILON || p.is_nan() {
+        let delta = c1 - p1;
+        return p1.extend(delta.y.atan2(delta.x));
+    }
+
+    let tt = p * p;
+    let ttt = tt * p;
+    let u = 1.0 - p;
+    let uu = u * u;
+    let uuu = uu * u;
+    let ut = u * p;
+    let ut3 = ut * 3.0;
+    let uut3 = u * ut3;
+    let utt3 = ut3 * p;
+    let point = p1 * uuu + c1 * uut3 + c2 * utt3 + p2 * ttt;
+
+    let r = if !tangents {
+        0.0
+    } else if p < 0.001 {
+        let delta = c1 - p1;
+        delta.y.atan2(delta.x)
+    } else {
+        let delta = point - (p1 * uu + c1 * ut * 2.0 + c2 * tt);
+        delta.y.atan2(delta.x)
+    };
+
+    point.extend(r)
+}
+
+/// Approximated length of a bezier curve, using forward differences with 4 segments
+fn bezier_length(p1: Vec2, c1: Vec2, c2: Vec2, p2: Vec2) -> f32 {
+    let tmp = (p1 - c1 * 2.0 + c2) * 0.1875;
+    let dddf = ((c1 - c2) * 3.0 - p1 + p2) * 0.09375;
+    let mut ddf = tmp * 2.0 + dddf;
+    let mut df = (c1 - p1) * 0.75 + tmp + dddf * 0.16666667;
+
+    let mut length = df.length();
+    df += ddf;
+    ddf += dddf;
+    length += df.length();
+    df += ddf;
+    length += df.length();
+    df += ddf + dddf;
+    length += df.length();
+    length
+}
+
+/// Accumulated lengths of 10 segments of a bezier curve, returns the curve length
+fn bezier_segments(p1: Vec2, c1: Vec2, c2: Vec2, p2: Vec2, segments: &mut [f32; 10]) -> f32 {
+    let tmp = (p1 - c1 * 2.0 + c2) * 0.03;
+    let dddf = ((c1 - c2) * 3.0 - p1 + p2) * 0.006;
+    let mut ddf = tmp * 2.0 + dddf;
+    let mut df = (c1 - p1) * 0.3 + tmp + dddf * 0.16666667;
+
+    let mut length = df.length();
+    segments[0] = length;
+    for segment in segments.iter_mut().take(8).skip(1) {
+        df += ddf;
+        ddf += dddf;
+        length += df.length();
+        *segment = length;
+    }
+    df += ddf;
+    length += df.length();
+    segments[8] = length;
+    df += ddf + dddf;
+    length += df.length();
+    segments[9] = length;
+    length
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attachment::AttachmentVertices;
+
+    /// Straight path along the `x` axis from 0 to 300 made of 3 curves
+    fn straight_path() -> (PathAttachment, Vec<Vec2>) {
+        let world: Vec<Vec2> = (0..12)
+            .map(|i| Vec2::new((i as f32 - 1.0) * 100.0 / 3.0, 0.0))
+            .collect();
+        let path = PathAttachment {
+            vertices: AttachmentVertices {
+                positions: world.clone(),
+                ..Default::default()
+            },
+            closed: false,
+            constant_speed: true,
+            lengths: vec![100.0, 200.0, 300.0, 0.0],
+        };
+        (path, world)
+    }
+
+    #[test]
+    fn bones_follow_path() {
+        let (path, world) = straight_path();
+
+        // Bones pointing up, along the `y` axis
+        let bone = Mat3::from_scale_angle_translation(Vec2::ONE, PI * 0.5, Vec2::ZERO);
+        let bones = vec![(bone, 50.0); 3];
+
+        let constraint = FollowPathConstraint {
+            position: 0.25,
+            spacing_mode: SpacingMode::Fixed,
+            spacing: 50.0,
+            rotate_mode: RotateMode::Chain,
+            ..Default::default()
+        };
+
+        let worlds = follow_path(&constraint, &path, &world, &Mat3::IDENTITY, &bones);
+        for (i, world) in worlds.iter().enumerate() {
+            let expected = Mat3::from_scale_angle_translation(
+                Vec2::ONE,
+                0.0,
+                Vec2::new(75.0 + 50.0 * i as f32, 0.0),
+            );
+            assert!(world.abs_diff_eq(expected, 1e-3), "bone {}: {:?}", i, world);
+        }
+    }
+
+    #[test]
+    fn bones_stretch_along_path() {
+        let (path, world) = straight_path();
+
+        let bone = Mat3::from_scale_angle_translation(Vec2::ONE, 0.0, Vec2::ZERO);
+        let bones = vec![(bone, 50.0); 2];
+
+        // Each bone covers half of the path
+        let constraint = FollowPathConstraint {
+            spacing_mode: SpacingMode::Percent,
+            spacing: 0.5,
+            rotate_mode: RotateMode::ChainScale,
+            ..Default::default()
+        };
+
+        let worlds = follow_path(&constraint, &path, &world, &Mat3::IDENTITY, &bones);
+        for (i, world) in worlds.iter().enumerate() {
+            let expected = Mat3::from_scale_angle_translation(
+                Vec2::new(3.0, 1.0),
+                0.0,
+                Vec2::new(150.0 * i as f32, 0.0),
+            );
+            assert!(world.abs_diff_eq(expected, 1e-3), "bone {}: {:?}", i, world);
+        }
+    }
+}
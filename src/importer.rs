@@ -0,0 +1,783 @@
+use std::ffi::OsStr;
+
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    prelude::*,
+    render::{
+        mesh::Indices,
+        pipeline::PrimitiveTopology,
+        texture::{ImageType, Texture},
+    },
+    utils::{BoxedFuture, HashMap},
+};
+
+use crate::{
+    animation::AnimationPlayer,
+    attachment::{
+        slot_world_inverse, AttachmentVertices, BoundingBoxAttachment, ClippingAttachment,
+        MeshAttachment, PathAttachment, PointAttachment,
+    },
+    bounds::SkeletonBounds,
+    constraints::{
+        CopyPositionConstraint, CopyRotationConstraint, CopyScaleConstraint, CopyShearConstraint,
+        FollowPathConstraint, IkConstraint, TransformConstraint,
+    },
+    entity::BoneBundleBase,
+    skeleton::{bone_setup_transform, Skeleton, SkinSequences, SkinSprites, SpineSkeleton},
+    skin::SkeletonSkin,
+    slot::{DrawOrder, Slot},
+    spine::{
+        atlas,
+        spine::{SkinAttachment, SlotBlend},
+        Atlas, Spine,
+    },
+    sprite::{By, Rotation, Sprite, SpriteBlend, SpriteBundleBase, SpriteShape},
+    transform::{
+        LocalToWorld, LocalToWorld2D, Transform2D, TransformBundle, TransformBundle2D,
+        TransformMode,
+    },
+};
+
+/// Name of the skin used by the setup pose
+pub(crate) const DEFAULT_SKIN: &str = "default";
+
+const EXTENSIONS: &[&str] = &["spine_json", "spine_skel"];
+
+/// Loads a spine skeleton along side with it's atlas (same file name but with the `.spine_atlas` extension),
+/// loading fails without it since unpacked sprites aren't supported yet;
+///
+/// The default asset is a [`SpineSkeleton`], also produces the following labeled assets:
+///
+/// - `"Spine"` the skeleton data [`Spine`];
+/// - `"Scene"` the skeleton [`Scene`] in the setup pose, with a [`Skeleton`] and [`AnimationPlayer`] in the root entity;
+/// - a [`Texture`] for each atlas page labeled by it's file name;
+/// - a [`Sprite`] for each atlas region labeled by the region name;
+/// - a [`Sprite`] for each skin attachment labeled as `"{skin}/{slot}/{attachment}"`;
+#[derive(Default)]
+pub struct SpineImpoter {
+    /// Transform environment of the skeleton scene
+    pub transform_mode: TransformMode,
+    /// Overrides if the atlas textures have premultiplied alpha, otherwise it's given by
+    /// [`AtlasPage::premultiplied_alpha`](atlas::AtlasPage::premultiplied_alpha)
+    pub premultiplied_alpha: Option<bool>,
+}
+
+impl AssetLoader for SpineImpoter {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, anyhow::Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let spine = if load_context.path().extension() == Some(OsStr::new("spine_skel")) {
+                Spine::parse_binary(bytes)?
+            } else {
+                Spine::parse(bytes)?
+            };
+            check_attachments_vertices(&spine)?;
+
+            let mut skins: HashMap<String, SkinSprites> = Default::default();
+            let mut sequences: HashMap<String, SkinSequences> = Default::default();
+
+            // Load atlas with the same name
+            if let Ok(bytes) = load_context
+                .read_asset_bytes(load_context.path().with_extension("spine_atlas"))
+                .await
+            {
+                let atlas = Atlas::parse(&bytes[..])?;
+
+                // Load one texture per page
+                let mut pages = Vec::with_capacity(atlas.pages.len());
+                for page in &atlas.pages {
+                    let texture_path = load_context.path().with_file_name(&page.name);
+                    let texture_extension = texture_path
+                        .extension()
+                        .map(OsStr::to_str)
+                        .flatten()
+                        .unwrap_or("");
+                    let texture_buffer = load_context.read_asset_bytes(&texture_path).await?;
+                    let texture = Texture::from_buffer(
+                        &texture_buffer[..],
+                        ImageType::Extension(texture_extension),
+                    )?;
+                    // TODO: Set texture format, filter and repeat attributes
+                    let texture =
+                        load_context.set_labeled_asset(&page.name, LoadedAsset::new(texture));
+
+                    let size: Vec2 = page.size.into();
+                    pages.push(PageTexture {
+                        texture,
+                        size_inv: size.recip(),
+                        pma: self
+                            .premultiplied_alpha
+                            .unwrap_or_else(|| page.premultiplied_alpha()),
+                    });
+                }
+
+                for region in &atlas.regions {
+                    let page = &pages[region.page];
+                    let atlas_size = page.size_inv;
+
+                    let size: Vec2 = region.size.into();
+                    let mut pivot: Vec2 = region.orig.into();
+                    let mut size_uv = size;
+
+                    if region.rotate {
+                        size_uv = Vec2::new(size_uv.y, size_uv.x);
+                        pivot = Vec2::new(pivot.y, pivot.x);
+                    }
+
+                    pivot *= size_uv.recip();
+                    size_uv *= atlas_size;
+
+                    // ! FIXME: 1 pixel line and row is been trimmed
+                    let mut min: Vec2 = region.xy.into();
+                    min *= atlas_size;
+                    let mut max: Vec2 = min + size_uv;
+                    std::mem::swap(&mut min.y, &mut max.y);
+
+                    let mut sprite = Sprite::with_shape(
+                        Some(page.texture.clone()),
+                        SpriteShape::Rect {
+                            min,
+                            max,
+                            rotation: if region.rotate {
+                                Rotation::CCW
+                            } else {
+                                Rotation::None
+                            },
+                            size,
+                            pivot,
+                            padding: None,
+                        },
+                    );
+                    sprite.name = Some(region.name.clone());
+                    sprite.pma = page.pma;
+
+                    load_context.set_labeled_asset(&region.name, LoadedAsset::new(sprite));
+                }
+
+                let regions: HashMap<&str, &atlas::Region> = atlas
+                    .regions
+                    .iter()
+                    .map(|region| (region.name.as_str(), region))
+                    .collect();
+
+                // Weighted meshes are baked in the setup pose
+                let bones_world = setup_bones_world(&spine);
+
+                for skin in &spine.skins {
+                    let skin_sprites = skins.entry(skin.name.clone()).or_default();
+                    let skin_sequences = sequences.entry(skin.name.clone()).or_default();
+                    for (slot_name, attachments) in &skin.attachments {
+                        for (attachment_name, attachment) in attachments {
+                            // Linked meshes use the mesh resolved from their parent
+                            let attachment = attachment.mesh().unwrap_or(attachment);
+                            let path = match attachment {
+                                SkinAttachment::Region { path, .. }
+                                | SkinAttachment::Mesh { path, .. } => {
+                                    path.as_ref().unwrap_or(attachment_name)
+                                }
+                                _ => continue,
+                            };
+
+                            let slot_world = spine
+                                .slots
+                                .iter()
+                                .find(|slot| &slot.name == slot_name)
+                                .and_then(|slot| {
+                                    spine.bones.iter().position(|bone| bone.name == slot.bone)
+                                })
+                                .map_or(Mat3::IDENTITY, |index| bones_world[index]);
+                            let sprite = |path: &str| {
+                                regions.get(path).map(|region| match attachment {
+                                    SkinAttachment::Region { .. } => region_attachment_sprite(
+                                        &pages[region.page],
+                                        region,
+                                        attachment,
+                                    ),
+                                    _ => mesh_attachment_sprite(
+                                        &pages[region.page],
+                                        region,
+                                        attachment,
+                                        &slot_world,
+                                        &bones_world,
+                                    ),
+                                })
+                            };
+
+                            let label = format!("{}/{}/{}", skin.name, slot_name, attachment_name);
+                            let handle = if let Some(sequence) = attachment.sequence() {
+                                // Frames without a region are left empty
+                                let frames: Vec<Option<Handle<Sprite>>> =
+                                    (0..sequence.count)
+                                        .map(|index| {
+                                            let mut sprite = sprite(&sequence.path(path, index))?;
+                                            let label = format!("{}/{}", label, index);
+                                            sprite.name = Some(label.clone());
+                                            Some(load_context.set_labeled_asset(
+                                                &label,
+                                                LoadedAsset::new(sprite),
+                                            ))
+                                        })
+                                        .collect();
+
+                                let handle = frames.get(sequence.setup).cloned().flatten();
+                                if frames.iter().any(Option::is_some) {
+                                    skin_sequences
+                                        .entry(slot_name.clone())
+                                        .or_default()
+                                        .insert(attachment_name.clone(), frames);
+                                }
+                                handle
+                            } else {
+                                sprite(path).map(|mut sprite| {
+                                    sprite.name = Some(label.clone());
+                                    load_context.set_labeled_asset(&label, LoadedAsset::new(sprite))
+                                })
+                            };
+
+                            if let Some(handle) = handle {
+                                skin_sprites
+                                    .entry(slot_name.clone())
+                                    .or_default()
+                                    .insert(attachment_name.clone(), handle);
+                            }
+                        }
+                    }
+                }
+            } else {
+                // TODO: Fallback sprites from the spine `spine.skeleton.images`
+                anyhow::bail!(
+                    "atlas \"{}\" not found, unpacked sprites aren't supported",
+                    load_context.path().with_extension("spine_atlas").display()
+                );
+            }
+
+            let mut world = World::default();
+            let default_skin = skins.get(DEFAULT_SKIN);
+            let (root, skeleton) = match self.transform_mode {
+                TransformMode::Transform2D => {
+                    let root = world
+                        .spawn()
+                        .insert_bundle(TransformBundle2D::default())
+                        .id();
+                    let skeleton =
+                        spawn_setup_pose::<LocalToWorld2D>(&mut world, root, &spine, default_skin);
+                    (root, skeleton)
+                }
+                TransformMode::Transform2D5 => {
+                    let root = world.spawn().insert_bundle(TransformBundle::default()).id();
+                    let skeleton =
+                        spawn_setup_pose::<LocalToWorld>(&mut world, root, &spine, default_skin);
+                    (root, skeleton)
+                }
+            };
+
+            let spine = load_context.set_labeled_asset("Spine", LoadedAsset::new(spine));
+            world
+                .entity_mut(root)
+                .insert(Skeleton {
+                    spine: spine.clone(),
+                    ..skeleton
+                })
+                .insert(SkeletonSkin::default())
+                .insert(SkeletonBounds::default())
+                .insert(AnimationPlayer::default());
+
+            let scene =
+                load_context.set_labeled_asset("Scene", LoadedAsset::new(Scene::new(world)));
+
+            load_context.set_default_asset(LoadedAsset::new(SpineSkeleton {
+                spine,
+                scene,
+                skins,
+                sequences,
+            }));
+
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        EXTENSIONS
+    }
+}
+
+/// Loaded atlas page
+struct PageTexture {
+    texture: Handle<Texture>,
+    /// Inverse of the texture size, used to normalize the uvs
+    size_inv: Vec2,
+    pma: bool,
+}
+
+/// Spawns the skeleton hierarchy under the `root` entity, where each bone is child of it's parent bone
+/// (or the root entity) and each slot is a child of it's bone, showing the attachment set in the setup pose
+fn spawn_setup_pose<M>(
+    world: &mut World,
+    root: Entity,
+    spine: &Spine,
+    default_skin: Option<&SkinSprites>,
+) -> Skeleton
+where
+    M: Default + Send + Sync + 'static,
+{
+    let mut skeleton = Skeleton::default();
+    let mut bones_lookup: HashMap<&str, Entity> = Default::default();
+
+    // TODO: Missing bone InheritTransform, length and color
+    for bone in &spine.bones {
+        let parent = bone
+            .parent
+            .as_ref()
+            .and_then(|parent_name| bones_lookup.get(parent_name.as_str()))
+            .copied()
+            .unwrap_or(root);
+
+        let entity = world
+            .spawn()
+            .insert_bundle(BoneBundleBase::<M> {
+                parent: Parent(parent),
+                name: Name::new(bone.name.clone()),
+                transform: bone_setup_transform(bone),
+                ..Default::default()
+            })
+            .id();
+
+        // ? NOTE: Keeps the parent `Children` in sync
+        world.entity_mut(parent).push_children(&[entity]);
+
+        bones_lookup.insert(&bone.name, entity);
+        skeleton.bones.push(entity);
+    }
+
+    let default_skin_attachments = spine
+        .skins
+        .iter()
+        .find(|skin| skin.name == DEFAULT_SKIN)
+        .map(|skin| &skin.attachments);
+
+    let mut slots_lookup: HashMap<&str, Entity> = Default::default();
+    for slot in &spine.slots {
+        let bone = *bones_lookup.get(slot.bone.as_str()).unwrap_or(&root);
+
+        let sprite = slot.attachment.as_ref().and_then(|attachment| {
+            default_skin
+                .and_then(|slots| slots.get(&slot.name))
+                .and_then(|attachments| attachments.get(attachment))
+        });
+
+        let mut slot_bundle = SpriteBundleBase::<Transform2D, M>::default();
+        if let Some(sprite) = sprite {
+            slot_bundle.sprite = sprite.clone();
+        } else {
+            // Nothing to show
+            slot_bundle.visible.is_visible = false;
+        }
+        slot_bundle.sprite_instance.color = Color::hex(&slot.color).unwrap_or(Color::WHITE);
+        slot_bundle.blend = match slot.blend {
+            None | Some(SlotBlend::Normal) => SpriteBlend::Normal,
+            Some(SlotBlend::Additive) => SpriteBlend::Additive,
+            Some(SlotBlend::Multiply) => SpriteBlend::Multiply,
+            Some(SlotBlend::Screen) => SpriteBlend::Screen,
+        };
+        if let Some(dark) = slot.dark.as_ref().and_then(|dark| Color::hex(dark).ok()) {
+            slot_bundle.sprite_instance.dark = dark;
+        }
+
+        let entity = world
+            .spawn()
+            .insert_bundle(slot_bundle)
+            .insert(Name::new(slot.name.clone()))
+            .insert(Slot {
+                attachment: slot.attachment.clone(),
+                ..Default::default()
+            })
+            .insert(DrawOrder(skeleton.slots.len()))
+            .id();
+
+        // Attachments data used at runtime
+        let attachment = slot.attachment.as_ref().and_then(|attachment| {
+            default_skin_attachments
+                .and_then(|slots| slots.get(&slot.name))
+                .and_then(|attachments| attachments.get(attachment))
+        });
+        if let Some(attachment) = attachment {
+            if let Some(path) = PathAttachment::from_skin_attachment(attachment, &skeleton.bones) {
+                world.entity_mut(entity).insert(path);
+            } else if let Some(mesh) = MeshAttachment::from_skin_attachment(
+                DEFAULT_SKIN,
+                slot.attachment.as_deref().unwrap_or_default(),
+                attachment,
+                &skeleton.bones,
+            ) {
+                world.entity_mut(entity).insert(mesh);
+            } else if let Some(clipping) =
+                ClippingAttachment::from_skin_attachment(attachment, &spine.slots, &skeleton.bones)
+            {
+                world.entity_mut(entity).insert(clipping);
+            } else if let Some(bounding_box) =
+                BoundingBoxAttachment::from_skin_attachment(attachment, &skeleton.bones)
+            {
+                world.entity_mut(entity).insert(bounding_box);
+            } else if let Some(point) = PointAttachment::from_skin_attachment(
+                slot.attachment.as_deref().unwrap_or_default(),
+                attachment,
+            ) {
+                world.entity_mut(entity).insert(point);
+            }
+        }
+
+        world.entity_mut(bone).push_children(&[entity]);
+        slots_lookup.insert(&slot.name, entity);
+        skeleton.slots.push(entity);
+    }
+
+    let bone_entity = |name: &String| *bones_lookup.get(name.as_str()).unwrap_or(&root);
+
+    // Constraints are spawned as children of the root, to be applied in the order given by the `order` field
+    let mut constraints = vec![];
+    for ik in &spine.ik {
+        let entity = world
+            .spawn()
+            .insert(Name::new(ik.name.clone()))
+            .insert(IkConstraint {
+                bones: ik.bones.iter().map(bone_entity).collect(),
+                target: bone_entity(&ik.target),
+                mix: ik.mix,
+                softness: ik.softness,
+                bend_positive: ik.bend_positive,
+                compress: ik.compress,
+                stretch: ik.stretch,
+                uniform: ik.uniform,
+            })
+            .id();
+
+        world.entity_mut(root).push_children(&[entity]);
+        skeleton.ik_constraints.push(entity);
+        constraints.push((ik.order, entity));
+    }
+
+    for transform in &spine.transform {
+        let entity = world
+            .spawn()
+            .insert(Name::new(transform.name.clone()))
+            .insert(TransformConstraint {
+                bones: transform.bones.iter().map(bone_entity).collect(),
+                target: bone_entity(&transform.target),
+                local: transform.local,
+                relative: transform.relative,
+            })
+            .insert(CopyRotationConstraint {
+                mix: transform.rotate_mix,
+                offset: transform.rotation.to_radians(),
+            })
+            .insert(CopyPositionConstraint {
+                mix: transform.translate_mix,
+                offset: Vec2::new(transform.x, transform.y),
+            })
+            .insert(CopyScaleConstraint {
+                mix: transform.scale_mix,
+                offset: Vec2::new(transform.scale_x, transform.scale_y),
+            })
+            .insert(CopyShearConstraint {
+                mix: transform.shear_mix,
+                offset: transform.shear_y.to_radians(),
+            })
+            .id();
+
+        world.entity_mut(root).push_children(&[entity]);
+        skeleton.transform_constraints.push(entity);
+        constraints.push((transform.order as usize, entity));
+    }
+
+    for path in &spine.path {
+        let entity = world
+            .spawn()
+            .insert(Name::new(path.name.clone()))
+            .insert(FollowPathConstraint {
+                bones: path.bones.iter().map(bone_entity).collect(),
+                target: *slots_lookup.get(path.target.as_str()).unwrap_or(&root),
+                position_mode: path.position_mode,
+                spacing_mode: path.spacing_mode,
+                rotate_mode: path.rotate_mode,
+                offset_rotation: path.rotation.to_radians(),
+                position: path.position,
+                spacing: path.spacing,
+                rotate_mix: path.rotate_mix,
+                translate_mix: path.translate_mix,
+            })
+            .id();
+
+        world.entity_mut(root).push_children(&[entity]);
+        skeleton.path_constraints.push(entity);
+        constraints.push((path.order, entity));
+    }
+
+    constraints.sort_by_key(|(order, _)| *order);
+    skeleton.constraints = constraints.into_iter().map(|(_, entity)| entity).collect();
+
+    skeleton
+}
+
+/// Quad of a region attachment, the attachment offset and the atlas region whitespace stripping
+/// are baked in the mesh vertices
+fn region_attachment_sprite(
+    page: &PageTexture,
+    region: &atlas::Region,
+    attachment: &SkinAttachment,
+) -> Sprite {
+    let (x, y, scale_x, scale_y, rotation, width, height, color) = match attachment {
+        SkinAttachment::Region {
+            x,
+            y,
+            scale_x,
+            scale_y,
+            rotation,
+            width,
+            height,
+            color,
+            ..
+        } => (
+            *x, *y, *scale_x, *scale_y, *rotation, *width, *height, color,
+        ),
+        _ => unreachable!("not a region attachment"),
+    };
+
+    let orig: Vec2 = region.orig.into();
+    let offset: Vec2 = region.offset.into();
+    let packed: Vec2 = region.size.into();
+
+    // Region size in world units
+    let region_scale = Vec2::new(width as f32, height as f32) / orig * Vec2::new(scale_x, scale_y);
+    let min = Vec2::new(-(width as f32) * scale_x, -(height as f32) * scale_y) * 0.5
+        + offset * region_scale;
+    let max = min + packed * region_scale;
+
+    let (sin, cos) = rotation.to_radians().sin_cos();
+    let position = |v: Vec2| [v.x * cos - v.y * sin + x, v.x * sin + v.y * cos + y];
+
+    let vertices = vec![
+        position(min),
+        position(Vec2::new(max.x, min.y)),
+        position(max),
+        position(Vec2::new(min.x, max.y)),
+    ];
+
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.set_attribute(Mesh::ATTRIBUTE_POSITION, vertices);
+    mesh.set_attribute(
+        Mesh::ATTRIBUTE_UV_0,
+        region_uvs(region, page.size_inv).to_vec(),
+    );
+    mesh.set_indices(Some(Indices::U32(vec![0, 1, 2, 0, 2, 3])));
+
+    let mut sprite = Sprite::with_shape(
+        Some(page.texture.clone()),
+        SpriteShape::Custom {
+            mesh: By::Value(mesh),
+        },
+    );
+    sprite.color_base = Color::hex(color).unwrap_or(Color::WHITE);
+    sprite.pma = page.pma;
+    sprite
+}
+
+/// Mesh of a mesh attachment, the attachment uvs are remapped to the atlas page;
+/// Weighted meshes are posed by the setup `bones_world`, relative to the `slot_world`
+fn mesh_attachment_sprite(
+    page: &PageTexture,
+    region: &atlas::Region,
+    attachment: &SkinAttachment,
+    slot_world: &Mat3,
+    bones_world: &[Mat3],
+) -> Sprite {
+    let (uvs, triangles, vertices, color) = match attachment {
+        SkinAttachment::Mesh {
+            uvs,
+            triangles,
+            vertices,
+            color,
+            ..
+        } => (uvs, triangles, vertices, color),
+        _ => unreachable!("not a mesh attachment"),
+    };
+
+    // Bones indices stand in for the bones entities
+    let bones: Vec<Entity> = (0..bones_world.len() as u32).map(Entity::new).collect();
+    // Vertices were checked by `check_attachments_vertices`
+    let attachment_vertices =
+        AttachmentVertices::new(vertices, uvs.len() / 2, &bones).unwrap_or_default();
+
+    let mut world = vec![];
+    attachment_vertices.compute_world(
+        slot_world,
+        &[],
+        |bone| bones_world.get(bone.id() as usize).copied(),
+        &mut world,
+    );
+
+    // A slot scaled to 0 keeps the world vertices, they're collapsed to a point anyway
+    let slot_world_inv = slot_world_inverse(slot_world).unwrap_or(Mat3::IDENTITY);
+    let vertices: Vec<[f32; 2]> = world
+        .iter()
+        .map(|vertex| slot_world_inv.transform_point2(*vertex).into())
+        .collect();
+
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.set_attribute(Mesh::ATTRIBUTE_POSITION, vertices);
+    mesh.set_attribute(Mesh::ATTRIBUTE_UV_0, mesh_uvs(region, page.size_inv, uvs));
+    mesh.set_indices(Some(Indices::U32(triangles.clone())));
+
+    let mut sprite = Sprite::with_shape(
+        Some(page.texture.clone()),
+        SpriteShape::Custom {
+            mesh: By::Value(mesh),
+        },
+    );
+    sprite.color_base = Color::hex(color).unwrap_or(Color::WHITE);
+    sprite.pma = page.pma;
+    sprite
+}
+
+/// Fails on the first skin attachment with invalid vertices, see [`AttachmentVertices::new`]
+fn check_attachments_vertices(spine: &Spine) -> anyhow::Result<()> {
+    // Bones indices stand in for the bones entities
+    let bones: Vec<Entity> = (0..spine.bones.len() as u32).map(Entity::new).collect();
+    for skin in &spine.skins {
+        for (slot_name, attachments) in &skin.attachments {
+            for (attachment_name, attachment) in attachments {
+                let vertices = match attachment {
+                    SkinAttachment::Path {
+                        vertex_count,
+                        vertices,
+                        ..
+                    }
+                    | SkinAttachment::Clipping {
+                        vertex_count,
+                        vertices,
+                        ..
+                    }
+                    | SkinAttachment::BoundingBox {
+                        vertex_count,
+                        vertices,
+                        ..
+                    } => AttachmentVertices::new(vertices, *vertex_count, &bones),
+                    SkinAttachment::Mesh { uvs, vertices, .. } => {
+                        AttachmentVertices::new(vertices, uvs.len() / 2, &bones)
+                    }
+                    _ => continue,
+                };
+                if let Err(err) = vertices {
+                    anyhow::bail!(
+                        "invalid vertices in attachment \"{}\" of slot \"{}\" in skin \"{}\": {}",
+                        attachment_name,
+                        slot_name,
+                        skin.name,
+                        err
+                    );
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// World matrices of the bones in the setup pose
+fn setup_bones_world(spine: &Spine) -> Vec<Mat3> {
+    let mut bones_world: Vec<Mat3> = Vec::with_capacity(spine.bones.len());
+    for bone in &spine.bones {
+        // Parents always come before their children
+        let parent_world = bone
+            .parent
+            .as_ref()
+            .and_then(|parent| spine.bones.iter().position(|b| &b.name == parent))
+            .map_or(Mat3::IDENTITY, |index| bones_world[index]);
+        bones_world.push(parent_world * bone_setup_transform(bone).compute_matrix());
+    }
+    bones_world
+}
+
+/// Remaps the mesh `uvs`, normalized to the region original size, to the atlas page;
+/// Accounts for the region whitespace stripping and rotation
+fn mesh_uvs(region: &atlas::Region, atlas_size_inv: Vec2, uvs: &[f32]) -> Vec<[f32; 2]> {
+    let xy: Vec2 = region.xy.into();
+    let orig: Vec2 = region.orig.into();
+    let offset: Vec2 = region.offset.into();
+    let packed: Vec2 = region.size.into();
+
+    if region.rotate {
+        // Region was packed rotated by 90 degrees
+        let min = (xy - Vec2::new(orig.y - offset.y - packed.y, orig.x - offset.x - packed.x))
+            * atlas_size_inv;
+        let size = Vec2::new(orig.y, orig.x) * atlas_size_inv;
+        uvs.chunks_exact(2)
+            .map(|uv| [min.x + uv[1] * size.x, min.y + (1.0 - uv[0]) * size.y])
+            .collect()
+    } else {
+        let min = (xy - Vec2::new(offset.x, orig.y - packed.y - offset.y)) * atlas_size_inv;
+        let size = orig * atlas_size_inv;
+        uvs.chunks_exact(2)
+            .map(|uv| [min.x + uv[0] * size.x, min.y + uv[1] * size.y])
+            .collect()
+    }
+}
+
+/// Region uvs in the lower-left, lower-right, top-right and top-left order
+fn region_uvs(region: &atlas::Region, atlas_size_inv: Vec2) -> [[f32; 2]; 4] {
+    let mut size: Vec2 = region.size.into();
+    if region.rotate {
+        size = Vec2::new(size.y, size.x);
+    }
+
+    let min: Vec2 = Vec2::from(region.xy) * atlas_size_inv;
+    let max: Vec2 = min + size * atlas_size_inv;
+
+    let lower_left = [min.x, max.y];
+    let lower_right = [max.x, max.y];
+    let top_right = [max.x, min.y];
+    let top_left = [min.x, min.y];
+
+    if region.rotate {
+        // Region was packed rotated by 90 degrees
+        [lower_right, top_right, top_left, lower_left]
+    } else {
+        [lower_left, lower_right, top_right, top_left]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mesh_uvs_match_region_uvs() {
+        for rotate in [false, true].iter() {
+            let region = atlas::Region {
+                name: "region".to_string(),
+                page: 0,
+                rotate: *rotate,
+                xy: atlas::Point { x: 10, y: 20 },
+                size: atlas::Size {
+                    width: 30,
+                    height: 40,
+                },
+                orig: atlas::Point { x: 30, y: 40 },
+                offset: atlas::Point { x: 0, y: 0 },
+                index: -1,
+                split: None,
+                pad: None,
+            };
+            let atlas_size_inv = Vec2::splat(1.0 / 128.0);
+
+            // Lower-left, lower-right, top-right and top-left corners of the region
+            let uvs = mesh_uvs(
+                &region,
+                atlas_size_inv,
+                &[0.0, 1.0, 1.0, 1.0, 1.0, 0.0, 0.0, 0.0],
+            );
+            assert_eq!(uvs, region_uvs(&region, atlas_size_inv).to_vec());
+        }
+    }
+}
@@ -1,163 +1,109 @@
-use std::ffi::OsStr;
-
-use bevy::{
-    asset::{AssetLoader, LoadedAsset},
-    prelude::*,
-    render::texture::{ImageType, Texture},
-    utils::HashMap,
-};
+use bevy::{app::PluginGroupBuilder, prelude::*};
 
+pub mod animation;
+mod attachment;
+mod bounds;
+mod clipping;
 pub mod constraints;
 mod entity;
+mod importer;
+mod skeleton;
+mod skin;
+mod slot;
 pub mod spine;
 pub mod sprite;
 pub mod transform;
 
+pub use attachment::*;
+pub use bounds::*;
+pub use clipping::*;
 pub use entity::*;
-use spine::Atlas;
-use sprite::{Rotation, Sprite, SpriteShape};
-use transform::{Transform2D, TransformBundle};
-
-// TODO: PluginsGroup our something like that
-
-pub struct SpineImpoter;
-
-const EXTENSIONS: &'static [&'static str] = &["spine_json"];
-
-impl AssetLoader for SpineImpoter {
-    fn load<'a>(
-        &'a self,
-        bytes: &'a [u8],
-        load_context: &'a mut bevy::asset::LoadContext,
-    ) -> bevy::utils::BoxedFuture<'a, anyhow::Result<(), anyhow::Error>> {
-        Box::pin(async move {
-            let spine = spine::Spine::parse(bytes)?;
-
-            // // List of loaded sprites
-            // let mut sprites: HashMap<String, Handle<Sprite>> = Default::default();
-
-            // Load atlas with the same name
-            if let Ok(bytes) = load_context
-                .read_asset_bytes(load_context.path().with_extension("spine_atlas"))
-                .await
-            {
-                let atlas = Atlas::parse(&bytes[..])?;
-
-                // Load texture
-                let texture_path = load_context.path().with_file_name(&atlas.name);
-                let texture_extension = texture_path
-                    .extension()
-                    .map(OsStr::to_str)
-                    .flatten()
-                    .unwrap_or("");
-                let texture_buffer = load_context.read_asset_bytes(&texture_path).await?;
-                let texture = Texture::from_buffer(
-                    &texture_buffer[..],
-                    ImageType::Extension(texture_extension),
-                )?;
-                // TODO: Set texture format, filter and repeat attributes
-                let texture =
-                    load_context.set_labeled_asset(&atlas.name, LoadedAsset::new(texture));
-
-                let mut atlas_size: Vec2 = atlas.size.into();
-                atlas_size = atlas_size.recip();
-
-                for region in &atlas.regions {
-                    let size: Vec2 = region.size.into();
-                    let mut pivot: Vec2 = region.orig.into();
-                    let mut size_uv = size;
-
-                    if region.rotate {
-                        size_uv = Vec2::new(size_uv.y, size_uv.x);
-                        pivot = Vec2::new(pivot.y, pivot.x);
-                    }
-
-                    pivot *= size_uv.recip();
-                    size_uv *= atlas_size;
-
-                    // ! FIXME: 1 pixel line and row is been trimmed
-                    let mut min: Vec2 = region.xy.into();
-                    min *= atlas_size;
-                    let mut max: Vec2 = min + size_uv;
-                    std::mem::swap(&mut min.y, &mut max.y);
-
-                    let sprite = Sprite::with_shape(
-                        Some(texture.clone()),
-                        SpriteShape::Rect {
-                            min,
-                            max,
-                            rotation: if region.rotate {
-                                Rotation::CCW
-                            } else {
-                                Rotation::None
-                            },
-                            size,
-                            pivot,
-                            padding: None,
-                        },
-                    );
-                    //sprite.name = Some(region.name.clone());
-
-                    //let sprite_handle =
-                    load_context.set_labeled_asset(&region.name, LoadedAsset::new(sprite));
-                }
-            } else {
-                // TODO: Fallback sprites from the spine `spine.skeleton.images`
-                todo!("unpacked sprites")
-            }
-
-            let mut world = World::default();
-            world
-                .spawn()
-                .insert_bundle(TransformBundle::default())
-                .with_children(|world_builder| {
-                    let mut bones_lookup: HashMap<String, Entity> = Default::default();
-                    let root = world_builder.parent_entity();
-                    // TODO: Missing bone InheritTransform, length and color
-                    for bone in &spine.bones {
-                        let entity = world_builder
-                            .spawn()
-                            .insert_bundle(BoneBundle2D5 {
-                                parent: Parent(
-                                    bone.parent
-                                        .as_ref()
-                                        .and_then(|parent_name| bones_lookup.get(parent_name))
-                                        .copied()
-                                        .unwrap_or_else(|| root),
-                                ),
-                                name: Name::new(bone.name.clone()),
-                                transform: Transform2D {
-                                    translation: Vec2::new(bone.x, bone.y),
-                                    rotation: bone.rotation,
-                                    scale: Vec2::new(bone.scale_x, bone.scale_y),
-                                    shear: Vec2::new(bone.shear_x, bone.shear_y),
-                                },
-                                ..Default::default()
-                            })
-                            .id();
-
-                        bones_lookup.insert(bone.name.clone(), entity);
-                    }
-                });
-
-            // TODO: Create scene here
-
-            Ok(())
-        })
-    }
+pub use importer::*;
+pub use skeleton::*;
+pub use skin::*;
+pub use slot::*;
+
+use animation::{AnimationPlayer, SpineAnimationPlugin};
+use constraints::{
+    CopyPositionConstraint, CopyRotationConstraint, CopyScaleConstraint, CopyShearConstraint,
+    FollowPathConstraint, IkConstraint, SpineConstraintPlugin, TransformConstraint,
+};
+use spine::{Atlas, Spine};
+use sprite::SpritePlugin;
+use transform::{Transform2D5Plugin, Transform2DPlugin, TransformMode};
+
+/// Spine plugins settings, includes the [`SpineAssetPlugin`], [`SpineAnimationPlugin`], [`SpineConstraintPlugin`],
+/// [`SpineAttachmentPlugin`], [`SpritePlugin`] and the transform plugin matching the [`TransformMode`];
+///
+/// Any of them can be disabled using `app.add_plugins_with(SpinePlugin::default(), |group| group.disable::<SpritePlugin>())`
+#[derive(Default, Debug, Clone)]
+pub struct SpinePlugin {
+    /// Transform environment used by the spawned skeletons
+    pub transform_mode: TransformMode,
+    /// Overrides if the atlas textures have premultiplied alpha, see [`SpineImpoter::premultiplied_alpha`]
+    pub premultiplied_alpha: Option<bool>,
+}
 
-    fn extensions(&self) -> &[&str] {
-        EXTENSIONS
+impl PluginGroup for SpinePlugin {
+    fn build(&mut self, group: &mut PluginGroupBuilder) {
+        match self.transform_mode {
+            TransformMode::Transform2D => group.add(Transform2DPlugin),
+            TransformMode::Transform2D5 => group.add(Transform2D5Plugin),
+        };
+
+        group
+            .add(SpritePlugin)
+            .add(SpineAssetPlugin {
+                transform_mode: self.transform_mode,
+                premultiplied_alpha: self.premultiplied_alpha,
+            })
+            .add(SpineAnimationPlugin)
+            .add(SpineConstraintPlugin {
+                transform_mode: self.transform_mode,
+            })
+            .add(SpineAttachmentPlugin {
+                transform_mode: self.transform_mode,
+            });
     }
 }
 
+/// Registers the [`SpineImpoter`], the [`Spine`], [`Atlas`] and [`SpineSkeleton`] assets
+/// and the components found in the skeleton scene
 #[derive(Default)]
-pub struct SpinePlugin;
+pub struct SpineAssetPlugin {
+    /// Transform environment of the imported skeletons
+    pub transform_mode: TransformMode,
+    /// Overrides if the atlas textures have premultiplied alpha, see [`SpineImpoter::premultiplied_alpha`]
+    pub premultiplied_alpha: Option<bool>,
+}
 
-impl Plugin for SpinePlugin {
+impl Plugin for SpineAssetPlugin {
     fn build(&self, app: &mut AppBuilder) {
-        let _ = app;
-        todo!()
+        app.add_asset::<Spine>()
+            .add_asset::<Atlas>()
+            .add_asset::<SpineSkeleton>()
+            .register_type::<Skeleton>()
+            .register_type::<SkeletonSkin>()
+            .register_type::<SkeletonBounds>()
+            .register_type::<Slot>()
+            .register_type::<DrawOrder>()
+            .register_type::<AnimationPlayer>()
+            .register_type::<IkConstraint>()
+            .register_type::<TransformConstraint>()
+            .register_type::<CopyRotationConstraint>()
+            .register_type::<CopyPositionConstraint>()
+            .register_type::<CopyScaleConstraint>()
+            .register_type::<CopyShearConstraint>()
+            .register_type::<FollowPathConstraint>()
+            .register_type::<PathAttachment>()
+            .register_type::<MeshAttachment>()
+            .register_type::<ClippingAttachment>()
+            .register_type::<BoundingBoxAttachment>()
+            .register_type::<PointAttachment>()
+            .add_asset_loader(SpineImpoter {
+                transform_mode: self.transform_mode,
+                premultiplied_alpha: self.premultiplied_alpha,
+            });
     }
 }
 
@@ -0,0 +1,124 @@
+use bevy::{
+    ecs::{
+        entity::{EntityMap, MapEntities, MapEntitiesError},
+        reflect::ReflectMapEntities,
+    },
+    prelude::*,
+    reflect::TypeUuid,
+    utils::HashMap,
+};
+
+use crate::{
+    spine::{spine::Bone, Spine},
+    sprite::Sprite,
+    transform::Transform2D,
+};
+
+/// Attachments sprites indexed by slot and attachment names
+pub type SkinSprites = HashMap<String, HashMap<String, Handle<Sprite>>>;
+
+/// Sprites of every frame of the attachments sequences indexed by slot and attachment names,
+/// frames without a region in the atlas are `None`
+pub type SkinSequences = HashMap<String, HashMap<String, Vec<Option<Handle<Sprite>>>>>;
+
+/// Imported spine skeleton, produced by the [`SpineImpoter`](crate::SpineImpoter)
+#[derive(Debug, TypeUuid)]
+#[uuid = "eeeacf4c-8277-442b-8809-d5159babe2f7"]
+pub struct SpineSkeleton {
+    /// Skeleton data as parsed from the source file, labeled as `"Spine"`
+    pub spine: Handle<Spine>,
+    /// Skeleton in the setup pose, labeled as `"Scene"`
+    pub scene: Handle<Scene>,
+    /// Sprites for every supported attachment indexed by the skin name,
+    /// attachments with a sequence use the setup frame sprite
+    pub skins: HashMap<String, SkinSprites>,
+    /// Sequences frames sprites indexed by the skin name
+    pub sequences: HashMap<String, SkinSequences>,
+}
+
+impl SpineSkeleton {
+    /// Finds the sprite used by the `attachment` of the `slot` in the given `skin`
+    pub fn attachment_sprite(
+        &self,
+        skin: &str,
+        slot: &str,
+        attachment: &str,
+    ) -> Option<&Handle<Sprite>> {
+        self.skins
+            .get(skin)
+            .and_then(|slots| slots.get(slot))
+            .and_then(|attachments| attachments.get(attachment))
+    }
+
+    /// Finds the sprite of the frame `index` of the `attachment` sequence
+    pub fn sequence_sprite(
+        &self,
+        skin: &str,
+        slot: &str,
+        attachment: &str,
+        index: usize,
+    ) -> Option<&Handle<Sprite>> {
+        self.sequences
+            .get(skin)
+            .and_then(|slots| slots.get(slot))
+            .and_then(|attachments| attachments.get(attachment))
+            .and_then(|frames| frames.get(index))
+            .and_then(Option::as_ref)
+    }
+}
+
+/// Skeleton instance, found in the root entity of the [`SpineSkeleton::scene`]
+#[derive(Default, Debug, Clone, Reflect)]
+#[reflect(Component, MapEntities)]
+pub struct Skeleton {
+    pub spine: Handle<Spine>,
+    /// Bones entities in the same order of [`Spine::bones`]
+    pub bones: Vec<Entity>,
+    /// Slots entities in the same order of [`Spine::slots`]
+    pub slots: Vec<Entity>,
+    /// Ik constraints entities in the same order of [`Spine::ik`]
+    pub ik_constraints: Vec<Entity>,
+    /// Transform constraints entities in the same order of [`Spine::transform`]
+    pub transform_constraints: Vec<Entity>,
+    /// Path constraints entities in the same order of [`Spine::path`]
+    pub path_constraints: Vec<Entity>,
+    /// Constraints entities of any kind, in the order they must be applied
+    pub constraints: Vec<Entity>,
+}
+
+impl MapEntities for Skeleton {
+    fn map_entities(&mut self, entity_map: &EntityMap) -> Result<(), MapEntitiesError> {
+        for entity in self
+            .bones
+            .iter_mut()
+            .chain(self.slots.iter_mut())
+            .chain(self.ik_constraints.iter_mut())
+            .chain(self.transform_constraints.iter_mut())
+            .chain(self.path_constraints.iter_mut())
+            .chain(self.constraints.iter_mut())
+        {
+            *entity = entity_map.get(*entity)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Bone [`Transform2D`] in the setup pose
+pub(crate) fn bone_setup_transform(bone: &Bone) -> Transform2D {
+    Transform2D {
+        translation: Vec2::new(bone.x, bone.y),
+        rotation: bone.rotation.to_radians(),
+        scale: Vec2::new(bone.scale_x, bone.scale_y),
+        shear: bone_shear(bone.shear_x, bone.shear_y),
+    }
+}
+
+/// Converts the spine shear angles (in degrees) to the [`Transform2D::shear`] factors
+///
+/// **NOTE** Spine shears each axis independently before rotating the bone
+/// while [`Transform2D`] shears after the rotation, this is only an approximation
+#[inline]
+pub(crate) fn bone_shear(x: f32, y: f32) -> Vec2 {
+    Vec2::new(x.to_radians().tan(), y.to_radians().tan())
+}
@@ -0,0 +1,223 @@
+use bevy::prelude::*;
+
+use crate::{
+    attachment::{
+        BoundingBoxAttachment, ClippingAttachment, MeshAttachment, PathAttachment, PointAttachment,
+    },
+    importer::DEFAULT_SKIN,
+    skeleton::{Skeleton, SpineSkeleton},
+    slot::Slot,
+    spine::{spine::Skin, Spine},
+    sprite::Sprite,
+};
+
+/// Skins shown by a [`Skeleton`], found in the root entity of the [`SpineSkeleton::scene`];
+///
+/// Many skins can be combined (mix-and-match), later skins replace the attachments of the former ones
+/// and the default skin is used for any attachment not found in them
+#[derive(Default, Debug, Clone, Reflect)]
+#[reflect(Component)]
+pub struct SkeletonSkin {
+    skins: Vec<String>,
+}
+
+impl SkeletonSkin {
+    pub fn new(skin: impl Into<String>) -> Self {
+        Self {
+            skins: vec![skin.into()],
+        }
+    }
+
+    /// Skins names, in the order they are combined
+    #[inline]
+    pub fn skins(&self) -> &[String] {
+        &self.skins
+    }
+
+    /// Replaces the current skins by `skin`
+    pub fn set_skin(&mut self, skin: impl Into<String>) {
+        self.skins.clear();
+        self.skins.push(skin.into());
+    }
+
+    /// Combines `skin` with the current skins
+    pub fn add_skin(&mut self, skin: impl Into<String>) {
+        let skin = skin.into();
+        self.skins.retain(|name| *name != skin);
+        self.skins.push(skin);
+    }
+
+    /// Only the default skin will be shown
+    pub fn clear(&mut self) {
+        self.skins.clear();
+    }
+}
+
+/// Activates the bones and constraints required by the [`SkeletonSkin`] and shows the attachment
+/// of every changed [`Slot`] (or every slot when the skin changes);
+/// Slots of inactive bones are hidden and inactive constraints are removed from the [`Skeleton::constraints`]
+pub fn skeleton_skin_system(
+    mut commands: Commands,
+    spines: Res<Assets<Spine>>,
+    spine_skeletons: Res<Assets<SpineSkeleton>>,
+    mut skeletons: Query<(Entity, &SkeletonSkin, &mut Skeleton)>,
+    changed_skins: Query<Entity, Changed<SkeletonSkin>>,
+    changed_slots: Query<Entity, Changed<Slot>>,
+    mut slots: Query<(&Slot, &mut Handle<Sprite>, &mut Visible)>,
+) {
+    for (entity, skeleton_skin, mut skeleton) in skeletons.iter_mut() {
+        let skin_changed = changed_skins.get(entity).is_ok();
+        if !skin_changed
+            && !skeleton
+                .slots
+                .iter()
+                .any(|slot| changed_slots.get(*slot).is_ok())
+        {
+            continue;
+        }
+
+        let spine = if let Some(spine) = spines.get(&skeleton.spine) {
+            spine
+        } else {
+            continue;
+        };
+        let sprites = spine_skeletons
+            .iter()
+            .map(|(_, spine_skeleton)| spine_skeleton)
+            .find(|spine_skeleton| spine_skeleton.spine == skeleton.spine);
+
+        let default_skin = spine.skins.iter().find(|skin| skin.name == DEFAULT_SKIN);
+        let skins: Vec<&Skin> = skeleton_skin
+            .skins
+            .iter()
+            .filter_map(|name| spine.skins.iter().find(|skin| skin.name == *name))
+            .collect();
+
+        // Skin bones also activate their parents
+        let bone_index = |name: &str| spine.bones.iter().position(|bone| bone.name == name);
+        let mut bones_active: Vec<bool> = spine.bones.iter().map(|bone| !bone.skin).collect();
+        for name in skins.iter().flat_map(|skin| skin.bones.iter()) {
+            let mut index = bone_index(name);
+            while let Some(i) = index {
+                bones_active[i] = true;
+                index = spine.bones[i]
+                    .parent
+                    .as_ref()
+                    .and_then(|parent| bone_index(parent));
+            }
+        }
+        let bone_active = |name: &str| bone_index(name).map_or(true, |i| bones_active[i]);
+        let slot_active = |name: &str| {
+            spine
+                .slots
+                .iter()
+                .find(|slot| slot.name == name)
+                .map_or(true, |slot| bone_active(&slot.bone))
+        };
+
+        if skin_changed {
+            let mut skin = Skin::default();
+            for other in &skins {
+                skin.add_skin(other);
+            }
+
+            let mut constraints = vec![];
+            for (ik, entity) in spine.ik.iter().zip(&skeleton.ik_constraints) {
+                if (!ik.skin || skin.ik.contains(&ik.name)) && bone_active(&ik.target) {
+                    constraints.push((ik.order, *entity));
+                }
+            }
+            for (transform, entity) in spine.transform.iter().zip(&skeleton.transform_constraints) {
+                if (!transform.skin || skin.transform.contains(&transform.name))
+                    && bone_active(&transform.target)
+                {
+                    constraints.push((transform.order as usize, *entity));
+                }
+            }
+            for (path, entity) in spine.path.iter().zip(&skeleton.path_constraints) {
+                if (!path.skin || skin.path.contains(&path.name)) && slot_active(&path.target) {
+                    constraints.push((path.order, *entity));
+                }
+            }
+            constraints.sort_by_key(|(order, _)| *order);
+            skeleton.constraints = constraints.into_iter().map(|(_, entity)| entity).collect();
+        }
+
+        for (slot, entity) in spine.slots.iter().zip(&skeleton.slots) {
+            if !skin_changed && changed_slots.get(*entity).is_err() {
+                continue;
+            }
+
+            let (slot_attachment, mut sprite, mut visible) =
+                if let Ok(slot_components) = slots.get_mut(*entity) {
+                    slot_components
+                } else {
+                    continue;
+                };
+
+            // Attachment of the last skin that has it, or from the default skin
+            let attachment = slot_attachment.attachment.as_ref().and_then(|name| {
+                skins
+                    .iter()
+                    .rev()
+                    .chain(default_skin.iter())
+                    .find_map(|skin| {
+                        skin.attachment(&slot.name, name)
+                            .map(|attachment| (*skin, name, attachment))
+                    })
+            });
+
+            let attachment_sprite = attachment.and_then(|(skin, name, _)| {
+                sprites.and_then(|sprites| {
+                    slot_attachment
+                        .sequence_index
+                        .and_then(|index| {
+                            sprites.sequence_sprite(&skin.name, &slot.name, name, index)
+                        })
+                        .or_else(|| sprites.attachment_sprite(&skin.name, &slot.name, name))
+                })
+            });
+            if let Some(attachment_sprite) = attachment_sprite {
+                if *sprite != *attachment_sprite {
+                    *sprite = attachment_sprite.clone();
+                }
+            }
+            visible.is_visible = attachment_sprite.is_some() && bone_active(&slot.bone);
+
+            let mut slot_commands = commands.entity(*entity);
+            slot_commands
+                .remove::<PathAttachment>()
+                .remove::<MeshAttachment>()
+                .remove::<ClippingAttachment>()
+                .remove::<BoundingBoxAttachment>()
+                .remove::<PointAttachment>();
+            if let Some((skin, name, attachment)) = attachment {
+                if let Some(path) =
+                    PathAttachment::from_skin_attachment(attachment, &skeleton.bones)
+                {
+                    slot_commands.insert(path);
+                } else if let Some(mesh) = MeshAttachment::from_skin_attachment(
+                    &skin.name,
+                    name,
+                    attachment,
+                    &skeleton.bones,
+                ) {
+                    slot_commands.insert(mesh);
+                } else if let Some(clipping) = ClippingAttachment::from_skin_attachment(
+                    attachment,
+                    &spine.slots,
+                    &skeleton.bones,
+                ) {
+                    slot_commands.insert(clipping);
+                } else if let Some(bounding_box) =
+                    BoundingBoxAttachment::from_skin_attachment(attachment, &skeleton.bones)
+                {
+                    slot_commands.insert(bounding_box);
+                } else if let Some(point) = PointAttachment::from_skin_attachment(name, attachment)
+                {
+                    slot_commands.insert(point);
+                }
+            }
+        }
+    }
+}
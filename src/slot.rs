@@ -0,0 +1,43 @@
+use bevy::prelude::*;
+
+use crate::transform::{LocalToWorld, LocalToWorld2D, Transform2D};
+
+/// Slot instance, found in every slot entity of the [`SpineSkeleton::scene`](crate::SpineSkeleton::scene);
+///
+/// The slot sprite is found by the attachment name in the skins of the [`SkeletonSkin`](crate::SkeletonSkin),
+/// while the slot color is given by the [`SpriteInstance::color`](crate::sprite::SpriteInstance::color)
+#[derive(Default, Debug, Clone, Reflect)]
+#[reflect(Component)]
+pub struct Slot {
+    /// Attachment shown by the slot, nothing is shown when `None`
+    pub attachment: Option<String>,
+    /// Frame of the attachment [`Sequence`](crate::spine::spine::Sequence), the setup frame is shown when `None`
+    pub sequence_index: Option<usize>,
+}
+
+/// Place of the slot in the skeleton draw order, slots with higher values are drawn on top
+#[derive(Default, Debug, Clone, Copy, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct DrawOrder(pub usize);
+
+/// Depth between consecutive slots in the draw order
+pub const DRAW_ORDER_DEPTH: f32 = 0.01;
+
+/// Offsets each slot along the skeleton z axis by it's [`DrawOrder`], only used in the
+/// [`TransformMode::Transform2D5`](crate::transform::TransformMode::Transform2D5) environment;
+///
+/// The slot [`LocalToWorld`] is computed from it's bone every frame, so it must run after
+/// the transforms propagation and the constraints are applied
+pub fn draw_order_system(
+    mut slots: Query<(&DrawOrder, &Transform2D, &Parent, &mut LocalToWorld)>,
+    bones: Query<&LocalToWorld, Without<DrawOrder>>,
+) {
+    for (draw_order, transform, parent, mut slot_world) in slots.iter_mut() {
+        if let Ok(bone_world) = bones.get(parent.0) {
+            let depth = Mat4::from_translation(Vec3::Z * draw_order.0 as f32 * DRAW_ORDER_DEPTH);
+            *slot_world = *bone_world
+                * LocalToWorld::from(LocalToWorld2D::from(*transform))
+                * LocalToWorld::from(depth);
+        }
+    }
+}
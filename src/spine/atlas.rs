@@ -3,18 +3,14 @@
 use std::io::{BufReader, Read};
 
 use anyhow::Error;
-use bevy::math::Vec2;
+use bevy::{math::Vec2, reflect::TypeUuid};
 use nom::*;
 
-// TODO: Multiple page support
-#[derive(Debug, Eq, PartialEq, Clone)]
+#[derive(Debug, Eq, PartialEq, Clone, TypeUuid)]
+#[uuid = "b8cf652e-b9d8-406a-8cb6-000e75980cfa"]
 pub struct Atlas {
-    pub name: String,
-    pub size: Size,
-    pub format: Format,
-    pub filter: Filter,
-    pub repeat: Repeat,
-    // TODO: pub pma: bool,
+    pub pages: Vec<AtlasPage>,
+    /// Regions of every page, see [`Region::page`]
     pub regions: Vec<Region>,
 }
 
@@ -29,6 +25,34 @@ impl Atlas {
     }
 }
 
+/// Atlas texture
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct AtlasPage {
+    /// Texture file name
+    pub name: String,
+    pub size: Size,
+    pub format: Format,
+    pub filter: Filter,
+    pub repeat: Repeat,
+    /// Texture colors are premultiplied by their alpha
+    pub pma: bool,
+}
+
+impl AtlasPage {
+    /// Texture colors are premultiplied by their alpha, older spine versions don't write the `pma` key
+    /// so it's also guessed by the `-pma` suffix of the texture file name (ignoring the page number)
+    pub fn premultiplied_alpha(&self) -> bool {
+        self.pma
+            || std::path::Path::new(&self.name)
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .map_or(false, |stem| {
+                    stem.trim_end_matches(|c: char| c.is_ascii_digit())
+                        .ends_with("-pma")
+                })
+    }
+}
+
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub enum Format {
     Alpha,
@@ -80,6 +104,8 @@ impl From<Size> for Vec2 {
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub struct Region {
     pub name: String,
+    /// Index of the [`AtlasPage`] containing this region
+    pub page: usize,
     pub rotate: bool,
     pub xy: Point,
     pub size: Size,
@@ -291,6 +317,7 @@ mod parser {
     fn named_region(name: &str) -> Region {
         Region {
             name: name.to_string(),
+            page: 0,
             rotate: false,
             xy: Point { x: 0, y: 0 },
             size: Size {
@@ -308,7 +335,9 @@ mod parser {
     named!(
         region<Region>,
         do_parse!(
-            name: map_res!(call!(not_line_ending), str::from_utf8)
+            // Next page starts
+            not!(page_header)
+                >> name: map_res!(call!(not_line_ending), str::from_utf8)
                 >> reg: fold_many1!(
                     region_value,
                     named_region(name),
@@ -419,50 +448,85 @@ mod parser {
         )
     );
 
+    named!(
+        page_header<AtlasPage>,
+        ws!(do_parse!(
+            name: map_res!(call!(not_line_ending), str::from_utf8) >>
+            size_p: ws!(separated_pair!(tag!("size"), tag!(":"), size)) >>
+            format_p: ws!(separated_pair!(tag!("format"), tag!(":"), header_format)) >>
+            filter_p: ws!(separated_pair!(tag!("filter"), tag!(":"), filter)) >>
+            repeat_p: ws!(separated_pair!(tag!("repeat"), tag!(":"), repeat_setting)) >>
+            pma_p: opt!(complete!(ws!(separated_pair!(tag!("pma"), tag!(":"), parse_bool)))) >>
+
+            (AtlasPage {
+                name: name.to_string(),
+                size: size_p.1,
+                format: format_p.1,
+                filter: filter_p.1,
+                repeat: repeat_p.1,
+                pma: pma_p.map_or(false, |(_, pma)| pma),
+            })
+        ))
+    );
+
+    named!(
+        page<(AtlasPage, Vec<Region>)>,
+        ws!(do_parse!(
+            header: page_header >>
+            regions: fold_many0!(region, Vec::new(), |mut acc: Vec<_>, item| {
+                acc.push(item);
+                acc
+            }) >>
+
+            (header, regions)
+        ))
+    );
+
     named!(pub atlas<Atlas>, ws!(do_parse!(
-        name: map_res!(call!(not_line_ending), str::from_utf8) >>
-        size_p: ws!(separated_pair!(tag!("size"), tag!(":"), size)) >>
-        format_p: ws!(separated_pair!(tag!("format"), tag!(":"), header_format)) >>
-        filter_p: ws!(separated_pair!(tag!("filter"), tag!(":"), filter)) >>
-        repeat_p: ws!(separated_pair!(tag!("repeat"), tag!(":"), repeat_setting)) >>
-        regions: fold_many0!(region, Vec::new(), |mut acc: Vec<_>, item| {
-            acc.push(item);
-            acc
-        }) >>
-
-        (Atlas {
-            name: name.to_string(),
-            size: size_p.1,
-            format: format_p.1,
-            filter: filter_p.1,
-            repeat: repeat_p.1,
-            regions,
-        })
+        atlas: fold_many1!(
+            page,
+            Atlas { pages: vec![], regions: vec![] },
+            |mut atlas: Atlas, (page, regions): (AtlasPage, Vec<Region>)| {
+                let index = atlas.pages.len();
+                atlas.pages.push(page);
+                atlas.regions.extend(regions.into_iter().map(|mut region| {
+                    region.page = index;
+                    region
+                }));
+                atlas
+            }
+        ) >>
+
+        (atlas)
     )));
 
     #[cfg(test)]
     mod tests {
         use super::super::*;
-        use super::{atlas, region};
+        use super::{atlas, page_header, region};
         use nom::IResult::Done;
 
         #[test]
         fn parse_full_atlas() {
             let answer = Atlas {
-                name: "BugSpine_tex.png".to_string(),
-                size: Size {
-                    width: 0,
-                    height: 0,
-                },
-                format: Format::RGBA8888,
-                filter: Filter {
-                    minification: FilterSetting::Linear,
-                    magnification: FilterSetting::Linear,
-                },
-                repeat: Repeat::No,
+                pages: vec![AtlasPage {
+                    name: "BugSpine_tex.png".to_string(),
+                    size: Size {
+                        width: 0,
+                        height: 0,
+                    },
+                    format: Format::RGBA8888,
+                    filter: Filter {
+                        minification: FilterSetting::Linear,
+                        magnification: FilterSetting::Linear,
+                    },
+                    repeat: Repeat::No,
+                    pma: false,
+                }],
                 regions: vec![
                     Region {
                         name: "bug_leg".to_string(),
+                        page: 0,
                         rotate: false,
                         xy: Point { x: 1, y: 47 },
                         size: Size {
@@ -477,6 +541,7 @@ mod parser {
                     },
                     Region {
                         name: "bug_body".to_string(),
+                        page: 0,
                         rotate: false,
                         xy: Point { x: 1, y: 1 },
                         size: Size {
@@ -491,6 +556,7 @@ mod parser {
                     },
                     Region {
                         name: "bug_eye".to_string(),
+                        page: 0,
                         rotate: false,
                         xy: Point { x: 34, y: 47 },
                         size: Size {
@@ -541,17 +607,20 @@ bug_eye
         #[test]
         fn parse_atlas() {
             let answer = Atlas {
-                name: "BugSpine_tex.png".to_string(),
-                size: Size {
-                    width: 0,
-                    height: 0,
-                },
-                format: Format::RGBA8888,
-                filter: Filter {
-                    minification: FilterSetting::Linear,
-                    magnification: FilterSetting::Linear,
-                },
-                repeat: Repeat::No,
+                pages: vec![AtlasPage {
+                    name: "BugSpine_tex.png".to_string(),
+                    size: Size {
+                        width: 0,
+                        height: 0,
+                    },
+                    format: Format::RGBA8888,
+                    filter: Filter {
+                        minification: FilterSetting::Linear,
+                        magnification: FilterSetting::Linear,
+                    },
+                    repeat: Repeat::No,
+                    pma: false,
+                }],
                 regions: Vec::new(),
             };
             let result = atlas(
@@ -565,10 +634,87 @@ bug_eye
             assert_eq!(result, Done(&b""[..], answer));
         }
 
+        #[test]
+        fn parse_multiple_pages() {
+            let result = atlas(
+                b"
+dragon.png
+size: 1024,1024
+format: RGBA8888
+filter: Linear,Linear
+repeat: none
+chin
+  rotate: true
+  xy: 856, 138
+  size: 214, 146
+  orig: 214, 146
+  offset: 0, 0
+  index: -1
+
+dragon2.png
+size: 1024,1024
+format: RGBA8888
+filter: Linear,Linear
+repeat: none
+head
+  rotate: false
+  xy: 2, 2
+  size: 296, 260
+  orig: 296, 260
+  offset: 0, 0
+  index: -1
+tail
+  rotate: false
+  xy: 300, 2
+  size: 120, 80
+  orig: 120, 80
+  offset: 0, 0
+  index: -1
+",
+            );
+
+            if let Done(rest, atlas) = result {
+                assert!(rest.is_empty());
+                assert_eq!(atlas.pages.len(), 2);
+                assert_eq!(atlas.pages[0].name, "dragon.png");
+                assert_eq!(atlas.pages[1].name, "dragon2.png");
+
+                let regions = atlas
+                    .regions
+                    .iter()
+                    .map(|region| (region.name.as_str(), region.page))
+                    .collect::<Vec<_>>();
+                assert_eq!(regions, vec![("chin", 0), ("head", 1), ("tail", 1)]);
+            } else {
+                panic!("failed to parse: {:?}", result);
+            }
+        }
+
+        #[test]
+        fn parse_pma_page() {
+            let result = page_header(
+                b"hero-pma.png
+size: 1024,256
+format: RGBA8888
+filter: Linear,Linear
+repeat: none
+pma: true
+body",
+            );
+
+            if let Done(rest, page) = result {
+                assert_eq!(rest, &b"body"[..]);
+                assert!(page.pma);
+            } else {
+                panic!("failed to parse: {:?}", result);
+            }
+        }
+
         #[test]
         fn parse_partial_region() {
             let answer = Region {
                 name: "bug_body".to_string(),
+                page: 0,
                 rotate: true,
                 xy: Point { x: 12, y: 11 },
                 size: Size {
@@ -598,6 +744,7 @@ bug_eye
         fn parse_full_region() {
             let answer = Region {
                 name: "bug_body".to_string(),
+                page: 0,
                 rotate: true,
                 xy: Point { x: 12, y: 11 },
                 size: Size {
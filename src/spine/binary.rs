@@ -0,0 +1,1108 @@
+//! Spine binary format reader, described here http://pt.esotericsoftware.com/spine-binary-format
+//!
+//! Only files exported by the editor version `3.8.x` are supported, the binary output is decoded into
+//! the same data model used by the json format, so both can be used interchangeably.
+
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{anyhow, bail, Error};
+
+use super::spine::*;
+
+const ATTACHMENT_REGION: u8 = 0;
+const ATTACHMENT_BOUNDING_BOX: u8 = 1;
+const ATTACHMENT_MESH: u8 = 2;
+const ATTACHMENT_LINKED_MESH: u8 = 3;
+const ATTACHMENT_PATH: u8 = 4;
+const ATTACHMENT_POINT: u8 = 5;
+const ATTACHMENT_CLIPPING: u8 = 6;
+
+const SLOT_ATTACHMENT: u8 = 0;
+const SLOT_COLOR: u8 = 1;
+const SLOT_TWO_COLOR: u8 = 2;
+
+const BONE_ROTATE: u8 = 0;
+const BONE_TRANSLATE: u8 = 1;
+const BONE_SCALE: u8 = 2;
+const BONE_SHEAR: u8 = 3;
+
+const PATH_POSITION: u8 = 0;
+const PATH_SPACING: u8 = 1;
+const PATH_MIX: u8 = 2;
+
+const CURVE_LINEAR: u8 = 0;
+const CURVE_STEPPED: u8 = 1;
+const CURVE_BEZIER: u8 = 2;
+
+pub(crate) fn parse(buffer: &[u8]) -> Result<Spine, Error> {
+    SkeletonBinary::new(buffer).read()
+}
+
+struct SkeletonBinary<'a> {
+    buffer: &'a [u8],
+    cursor: usize,
+    strings: Vec<String>,
+    nonessential: bool,
+    bones: Vec<String>,
+    slots: Vec<String>,
+    ik: Vec<String>,
+    transform: Vec<String>,
+    path: Vec<String>,
+    skins: Vec<String>,
+    /// Events data and whether they have an audio path, even an empty one
+    events: Vec<(String, Event, bool)>,
+    /// Every vertex attachment by skin and slot indices, deform timelines can only target those
+    vertex_attachments: HashSet<(usize, usize, String)>,
+}
+
+impl<'a> SkeletonBinary<'a> {
+    fn new(buffer: &'a [u8]) -> Self {
+        Self {
+            buffer,
+            cursor: 0,
+            strings: vec![],
+            nonessential: false,
+            bones: vec![],
+            slots: vec![],
+            ik: vec![],
+            transform: vec![],
+            path: vec![],
+            skins: vec![],
+            events: vec![],
+            vertex_attachments: Default::default(),
+        }
+    }
+
+    fn read(mut self) -> Result<Spine, Error> {
+        let skeleton = self.read_skeleton()?;
+
+        // Strings
+        let count = self.read_varint()?;
+        for _ in 0..count {
+            let string = self.read_string()?.unwrap_or_default();
+            self.strings.push(string);
+        }
+
+        // Bones
+        let mut bones = vec![];
+        let count = self.read_varint()?;
+        for i in 0..count {
+            let bone = self.read_bone(i == 0)?;
+            self.bones.push(bone.name.clone());
+            bones.push(bone);
+        }
+
+        // Slots
+        let mut slots = vec![];
+        let count = self.read_varint()?;
+        for _ in 0..count {
+            let slot = self.read_slot()?;
+            self.slots.push(slot.name.clone());
+            slots.push(slot);
+        }
+
+        // Constraints
+        let mut ik = vec![];
+        let count = self.read_varint()?;
+        for _ in 0..count {
+            let constraint = self.read_ik_constraint()?;
+            self.ik.push(constraint.name.clone());
+            ik.push(constraint);
+        }
+
+        let mut transform = vec![];
+        let count = self.read_varint()?;
+        for _ in 0..count {
+            let constraint = self.read_transform_constraint()?;
+            self.transform.push(constraint.name.clone());
+            transform.push(constraint);
+        }
+
+        let mut path = vec![];
+        let count = self.read_varint()?;
+        for _ in 0..count {
+            let constraint = self.read_path_constraint()?;
+            self.path.push(constraint.name.clone());
+            path.push(constraint);
+        }
+
+        // Skins
+        let mut skins = vec![];
+        if let Some(skin) = self.read_skin(true)? {
+            self.skins.push(skin.name.clone());
+            skins.push(skin);
+        }
+        let count = self.read_varint()?;
+        for _ in 0..count {
+            let skin = self.read_skin(false)?.unwrap();
+            self.skins.push(skin.name.clone());
+            skins.push(skin);
+        }
+
+        // Events
+        let count = self.read_varint()?;
+        for _ in 0..count {
+            let name = self.read_string_ref()?.unwrap_or_default();
+            let int = self.read_varint_signed()? as i64;
+            let float = self.read_f32()?;
+            // Empty strings are omitted in json
+            let string = self.read_string()?.filter(|s| !s.is_empty());
+            let audio = self.read_string()?;
+            let has_audio = audio.is_some();
+            let mut event = Event {
+                int,
+                float,
+                string,
+                audio: audio.filter(|s| !s.is_empty()),
+                volume: 1.0,
+                balance: 0.0,
+            };
+            // Volume and balance follow any audio path, even an empty one
+            if has_audio {
+                event.volume = self.read_f32()?;
+                event.balance = self.read_f32()?;
+            }
+            self.events.push((name, event, has_audio));
+        }
+
+        // Animations
+        let mut animations = HashMap::default();
+        let count = self.read_varint()?;
+        for _ in 0..count {
+            let name = self.read_string()?.unwrap_or_default();
+            let animation = self
+                .read_animation()
+                .map_err(|err| err.context(format!("animation \"{}\"", name)))?;
+            animations.insert(name, animation);
+        }
+
+        Ok(Spine {
+            slots,
+            skeleton,
+            animations,
+            skins,
+            bones,
+            transform,
+            ik,
+            path,
+            events: self
+                .events
+                .into_iter()
+                .map(|(name, event, _)| (name, event))
+                .collect(),
+        })
+    }
+
+    fn read_skeleton(&mut self) -> Result<Skeleton, Error> {
+        let hash = self.read_string()?;
+        let spine = self.read_string()?.unwrap_or_default();
+        if !spine.starts_with("3.8") {
+            bail!(
+                "unsupported spine binary version \"{}\", expected: \"3.8.x\"",
+                spine
+            );
+        }
+
+        let x = self.read_f32()?;
+        let y = self.read_f32()?;
+        let width = self.read_f32()?;
+        let height = self.read_f32()?;
+
+        let mut skeleton = Skeleton {
+            hash,
+            spine,
+            x,
+            y,
+            width,
+            height,
+            fps: 30,
+            images: String::new(),
+            audio: String::new(),
+        };
+
+        self.nonessential = self.read_bool()?;
+        if self.nonessential {
+            skeleton.fps = self.read_f32()? as u32;
+            skeleton.images = self.read_string()?.unwrap_or_default();
+            skeleton.audio = self.read_string()?.unwrap_or_default();
+        }
+
+        Ok(skeleton)
+    }
+
+    fn read_bone(&mut self, root: bool) -> Result<Bone, Error> {
+        let name = self.read_string()?.unwrap_or_default();
+        let parent = if root { None } else { Some(self.bone_name()?) };
+
+        let mut bone = Bone {
+            name,
+            parent,
+            rotation: self.read_f32()?,
+            x: self.read_f32()?,
+            y: self.read_f32()?,
+            scale_x: self.read_f32()?,
+            scale_y: self.read_f32()?,
+            shear_x: self.read_f32()?,
+            shear_y: self.read_f32()?,
+            length: self.read_f32()?,
+            transform: match self.read_varint()? {
+                0 => InheritTransform::Normal,
+                1 => InheritTransform::OnlyTranslation,
+                2 => InheritTransform::NoRotationOrReflection,
+                3 => InheritTransform::NoScale,
+                4 => InheritTransform::NoScaleOrReflection,
+                v => bail!("invalid bone transform mode {}", v),
+            },
+            skin: self.read_bool()?,
+            inherit_scale: true,
+            inherit_rotation: true,
+            color: "9B9B9BFF".to_string(),
+        };
+
+        if self.nonessential {
+            bone.color = self.read_color()?;
+        }
+
+        Ok(bone)
+    }
+
+    fn read_slot(&mut self) -> Result<Slot, Error> {
+        let name = self.read_string()?.unwrap_or_default();
+        let bone = self.bone_name()?;
+        let color = self.read_color()?;
+        let dark = self.read_i32()?;
+        let dark = if dark == -1 {
+            None
+        } else {
+            Some(format!("{:06x}", dark as u32 & 0x00FF_FFFF))
+        };
+        let attachment = self.read_string_ref()?;
+        let blend = match self.read_varint()? {
+            0 => None,
+            1 => Some(SlotBlend::Additive),
+            2 => Some(SlotBlend::Multiply),
+            3 => Some(SlotBlend::Screen),
+            v => bail!("invalid slot blend mode {}", v),
+        };
+
+        Ok(Slot {
+            name,
+            bone,
+            color,
+            dark,
+            attachment,
+            blend,
+        })
+    }
+
+    fn read_ik_constraint(&mut self) -> Result<IkConstraints, Error> {
+        let name = self.read_string()?.unwrap_or_default();
+        let order = self.read_varint()?;
+        let skin = self.read_bool()?;
+        let bones = self.read_bone_names()?;
+        let target = self.bone_name()?;
+
+        Ok(IkConstraints {
+            name,
+            order,
+            skin,
+            bones,
+            target,
+            mix: self.read_f32()?,
+            softness: self.read_f32()?,
+            bend_positive: self.read_u8()? as i8 > 0,
+            compress: self.read_bool()?,
+            stretch: self.read_bool()?,
+            uniform: self.read_bool()?,
+        })
+    }
+
+    fn read_transform_constraint(&mut self) -> Result<TransformConstraints, Error> {
+        let name = self.read_string()?.unwrap_or_default();
+        let order = self.read_varint()? as u32;
+        let skin = self.read_bool()?;
+        let bones = self.read_bone_names()?;
+        let target = self.bone_name()?;
+
+        Ok(TransformConstraints {
+            name,
+            order,
+            skin,
+            bones,
+            target,
+            local: self.read_bool()?,
+            relative: self.read_bool()?,
+            rotation: self.read_f32()?,
+            x: self.read_f32()?,
+            y: self.read_f32()?,
+            scale_x: self.read_f32()?,
+            scale_y: self.read_f32()?,
+            shear_x: 0.0,
+            shear_y: self.read_f32()?,
+            rotate_mix: self.read_f32()?,
+            translate_mix: self.read_f32()?,
+            scale_mix: self.read_f32()?,
+            shear_mix: self.read_f32()?,
+        })
+    }
+
+    fn read_path_constraint(&mut self) -> Result<PathConstraints, Error> {
+        let name = self.read_string()?.unwrap_or_default();
+        let order = self.read_varint()?;
+        let skin = self.read_bool()?;
+        let bones = self.read_bone_names()?;
+        let target = self.slot_name()?;
+
+        Ok(PathConstraints {
+            name,
+            order,
+            skin,
+            bones,
+            target,
+            position_mode: match self.read_varint()? {
+                0 => PositionMode::Fixed,
+                1 => PositionMode::Percent,
+                v => bail!("invalid path position mode {}", v),
+            },
+            spacing_mode: match self.read_varint()? {
+                0 => SpacingMode::Length,
+                1 => SpacingMode::Fixed,
+                2 => SpacingMode::Percent,
+                v => bail!("invalid path spacing mode {}", v),
+            },
+            rotate_mode: match self.read_varint()? {
+                0 => RotateMode::Tangent,
+                1 => RotateMode::Chain,
+                2 => RotateMode::ChainScale,
+                v => bail!("invalid path rotate mode {}", v),
+            },
+            rotation: self.read_f32()?,
+            position: self.read_f32()?,
+            spacing: self.read_f32()?,
+            rotate_mix: self.read_f32()?,
+            translate_mix: self.read_f32()?,
+        })
+    }
+
+    fn read_skin(&mut self, default: bool) -> Result<Option<Skin>, Error> {
+        let skin_index = self.skins.len();
+        let mut skin = Skin::default();
+
+        let slot_count;
+        if default {
+            slot_count = self.read_varint()?;
+            if slot_count == 0 {
+                return Ok(None);
+            }
+            skin.name = "default".to_string();
+        } else {
+            skin.name = self.read_string_ref()?.unwrap_or_default();
+            skin.bones = self.read_bone_names()?;
+
+            let count = self.read_varint()?;
+            for _ in 0..count {
+                let index = self.read_varint()?;
+                skin.ik.push(lookup(&self.ik, index, "ik constraint")?);
+            }
+
+            let count = self.read_varint()?;
+            for _ in 0..count {
+                let index = self.read_varint()?;
+                skin.transform
+                    .push(lookup(&self.transform, index, "transform constraint")?);
+            }
+
+            let count = self.read_varint()?;
+            for _ in 0..count {
+                let index = self.read_varint()?;
+                skin.path
+                    .push(lookup(&self.path, index, "path constraint")?);
+            }
+
+            slot_count = self.read_varint()?;
+        }
+
+        for _ in 0..slot_count {
+            let slot_index = self.read_varint()?;
+            let slot_name = lookup(&self.slots, slot_index, "slot")?;
+
+            let count = self.read_varint()?;
+            for _ in 0..count {
+                let name = self.read_string_ref()?.unwrap_or_default();
+                let attachment = self
+                    .read_attachment(skin_index, slot_index, &name)
+                    .map_err(|err| {
+                        err.context(format!(
+                            "skin \"{}\", slot \"{}\", attachment \"{}\"",
+                            skin.name, slot_name, name
+                        ))
+                    })?;
+
+                if let Some(attachment) = attachment {
+                    skin.attachments
+                        .entry(slot_name.clone())
+                        .or_default()
+                        .insert(name, attachment);
+                }
+            }
+        }
+
+        Ok(Some(skin))
+    }
+
+    fn read_attachment(
+        &mut self,
+        skin_index: usize,
+        slot_index: usize,
+        attachment_name: &str,
+    ) -> Result<Option<SkinAttachment>, Error> {
+        // Name used by the attachment, defaults to the skin placeholder name
+        let name = self.read_string_ref()?;
+        let path = |path: Option<String>| {
+            path.or_else(|| name.clone().filter(|name| name != attachment_name))
+        };
+
+        match self.read_u8()? {
+            ATTACHMENT_REGION => {
+                let path = path(self.read_string_ref()?);
+                let rotation = self.read_f32()?;
+                let x = self.read_f32()?;
+                let y = self.read_f32()?;
+                let scale_x = self.read_f32()?;
+                let scale_y = self.read_f32()?;
+                let width = self.read_f32()? as u32;
+                let height = self.read_f32()? as u32;
+                let color = self.read_color()?;
+
+                Ok(Some(SkinAttachment::Region {
+                    path,
+                    x,
+                    y,
+                    scale_x,
+                    scale_y,
+                    rotation,
+                    width,
+                    height,
+                    color,
+                    sequence: None,
+                }))
+            }
+            ATTACHMENT_BOUNDING_BOX => {
+                let vertex_count = self.read_varint()?;
+                let vertices = self.read_vertices(vertex_count)?;
+                self.register_vertices(skin_index, slot_index, attachment_name);
+                let color = if self.nonessential {
+                    self.read_color()?
+                } else {
+                    "60f000ff".to_string()
+                };
+
+                Ok(Some(SkinAttachment::BoundingBox {
+                    vertex_count,
+                    vertices,
+                    color,
+                }))
+            }
+            ATTACHMENT_MESH => {
+                let path = path(self.read_string_ref()?);
+                let color = self.read_color()?;
+                let vertex_count = self.read_varint()?;
+                let uvs = self.read_f32_array(vertex_count << 1)?;
+                let triangles = self.read_u16_array()?;
+                let vertices = self.read_vertices(vertex_count)?;
+                self.register_vertices(skin_index, slot_index, attachment_name);
+                let hull = self.read_varint()? as u32;
+
+                let mut edges = None;
+                let mut width = None;
+                let mut height = None;
+                if self.nonessential {
+                    edges = Some(self.read_u16_array()?);
+                    width = Some(self.read_f32()? as u32);
+                    height = Some(self.read_f32()? as u32);
+                }
+
+                Ok(Some(SkinAttachment::Mesh {
+                    path,
+                    uvs,
+                    triangles,
+                    vertices,
+                    hull,
+                    edges,
+                    color,
+                    width,
+                    height,
+                    sequence: None,
+                }))
+            }
+            ATTACHMENT_LINKED_MESH => {
+                let path = path(self.read_string_ref()?);
+                let color = self.read_color()?;
+                let skin = self.read_string_ref()?;
+                let parent = self.read_string_ref()?.unwrap_or_default();
+                let deform = self.read_bool()?;
+                self.register_vertices(skin_index, slot_index, attachment_name);
+
+                let mut width = None;
+                let mut height = None;
+                if self.nonessential {
+                    width = Some(self.read_f32()? as u32);
+                    height = Some(self.read_f32()? as u32);
+                }
+
+                Ok(Some(SkinAttachment::LinkedMesh {
+                    path,
+                    skin,
+                    parent,
+                    deform,
+                    color,
+                    width,
+                    height,
+                    sequence: None,
+                    mesh: None,
+                }))
+            }
+            ATTACHMENT_PATH => {
+                let closed = self.read_bool()?;
+                let constant_speed = self.read_bool()?;
+                let vertex_count = self.read_varint()?;
+                let vertices = self.read_vertices(vertex_count)?;
+                self.register_vertices(skin_index, slot_index, attachment_name);
+                let lengths = self.read_f32_array(vertex_count / 3)?;
+                let color = if self.nonessential {
+                    self.read_color()?
+                } else {
+                    "ff7f00ff".to_string()
+                };
+
+                Ok(Some(SkinAttachment::Path {
+                    closed,
+                    constant_speed,
+                    lengths,
+                    vertex_count,
+                    vertices,
+                    color,
+                }))
+            }
+            ATTACHMENT_POINT => {
+                let rotation = self.read_f32()?;
+                let x = self.read_f32()?;
+                let y = self.read_f32()?;
+                let color = if self.nonessential {
+                    self.read_color()?
+                } else {
+                    "f1f100ff".to_string()
+                };
+
+                Ok(Some(SkinAttachment::Point {
+                    x,
+                    y,
+                    rotation,
+                    color,
+                }))
+            }
+            ATTACHMENT_CLIPPING => {
+                let end = Some(self.slot_name()?);
+                let vertex_count = self.read_varint()?;
+                let vertices = self.read_vertices(vertex_count)?;
+                self.register_vertices(skin_index, slot_index, attachment_name);
+                let color = if self.nonessential {
+                    self.read_color()?
+                } else {
+                    "ce3a3aff".to_string()
+                };
+
+                Ok(Some(SkinAttachment::Clipping {
+                    end,
+                    vertex_count,
+                    vertices,
+                    color,
+                }))
+            }
+            v => bail!("invalid attachment type {}", v),
+        }
+    }
+
+    /// Reads vertices using the same layout as json, weighted vertices are stored as
+    /// `[bone_count, bone_index, x, y, weight, ...]`
+    fn read_vertices(&mut self, vertex_count: usize) -> Result<Vec<f32>, Error> {
+        if !self.read_bool()? {
+            return self.read_f32_array(vertex_count << 1);
+        }
+
+        let mut values = vec![];
+        for _ in 0..vertex_count {
+            let bone_count = self.read_varint()?;
+            values.push(bone_count as f32);
+            for _ in 0..bone_count {
+                values.push(self.read_varint()? as f32);
+                values.push(self.read_f32()?);
+                values.push(self.read_f32()?);
+                values.push(self.read_f32()?);
+            }
+        }
+
+        Ok(values)
+    }
+
+    fn register_vertices(&mut self, skin_index: usize, slot_index: usize, attachment_name: &str) {
+        self.vertex_attachments
+            .insert((skin_index, slot_index, attachment_name.to_string()));
+    }
+
+    fn read_animation(&mut self) -> Result<Animation, Error> {
+        let mut animation = Animation::default();
+
+        // Slot timelines
+        let count = self.read_varint()?;
+        for _ in 0..count {
+            let slot = self.slot_name()?;
+            let mut timelines = AnimationSlot::default();
+
+            let timeline_count = self.read_varint()?;
+            for _ in 0..timeline_count {
+                let timeline_type = self.read_u8()?;
+                let frame_count = self.read_varint()?;
+                match timeline_type {
+                    SLOT_ATTACHMENT => {
+                        for _ in 0..frame_count {
+                            timelines.attachment.push(AttachmentKeyframe {
+                                time: self.read_f32()?,
+                                name: self.read_string_ref()?,
+                            });
+                        }
+                    }
+                    SLOT_COLOR => {
+                        for frame in 0..frame_count {
+                            let time = self.read_f32()?;
+                            let color = self.read_color()?;
+                            let curve = self.read_curve(frame, frame_count)?;
+                            timelines.color.push(ColorKeyframe { time, color, curve });
+                        }
+                    }
+                    SLOT_TWO_COLOR => {
+                        for frame in 0..frame_count {
+                            let time = self.read_f32()?;
+                            let light = self.read_color()?;
+                            let dark = format!("{:06x}", self.read_i32()? as u32 & 0x00FF_FFFF);
+                            let curve = self.read_curve(frame, frame_count)?;
+                            timelines.two_color.push(TwoColorKeyframe {
+                                time,
+                                light,
+                                dark,
+                                curve,
+                            });
+                        }
+                    }
+                    v => bail!("invalid slot timeline type {}", v),
+                }
+            }
+
+            animation.slots.insert(slot, timelines.into());
+        }
+
+        // Bone timelines
+        let count = self.read_varint()?;
+        for _ in 0..count {
+            let bone = self.bone_name()?;
+            let mut timelines = AnimationBone::default();
+
+            let timeline_count = self.read_varint()?;
+            for _ in 0..timeline_count {
+                let timeline_type = self.read_u8()?;
+                let frame_count = self.read_varint()?;
+                for frame in 0..frame_count {
+                    let time = self.read_f32()?;
+                    match timeline_type {
+                        BONE_ROTATE => {
+                            let angle = self.read_f32()?;
+                            let curve = self.read_curve(frame, frame_count)?;
+                            timelines.rotate.push(RotateKeyframe { time, angle, curve });
+                        }
+                        BONE_TRANSLATE => {
+                            let x = self.read_f32()?;
+                            let y = self.read_f32()?;
+                            let curve = self.read_curve(frame, frame_count)?;
+                            timelines
+                                .translate
+                                .push(TranslateKeyframe { time, x, y, curve });
+                        }
+                        BONE_SCALE => {
+                            let x = self.read_f32()?;
+                            let y = self.read_f32()?;
+                            let curve = self.read_curve(frame, frame_count)?;
+                            timelines.scale.push(ScaleKeyframe { time, x, y, curve });
+                        }
+                        BONE_SHEAR => {
+                            let x = self.read_f32()?;
+                            let y = self.read_f32()?;
+                            let curve = self.read_curve(frame, frame_count)?;
+                            timelines.shear.push(ShearKeyframe { time, x, y, curve });
+                        }
+                        v => bail!("invalid bone timeline type {}", v),
+                    }
+                }
+            }
+
+            animation.bones.insert(bone, timelines.into());
+        }
+
+        // Ik constraint timelines
+        let count = self.read_varint()?;
+        for _ in 0..count {
+            let index = self.read_varint()?;
+            let name = lookup(&self.ik, index, "ik constraint")?;
+            let mut keyframes = vec![];
+
+            let frame_count = self.read_varint()?;
+            for frame in 0..frame_count {
+                keyframes.push(AnimationIk {
+                    time: self.read_f32()?,
+                    mix: self.read_f32()?,
+                    softness: self.read_f32()?,
+                    bend_positive: self.read_u8()? as i8 > 0,
+                    compress: self.read_bool()?,
+                    stretch: self.read_bool()?,
+                    curve: self.read_curve(frame, frame_count)?,
+                });
+            }
+
+            animation.ik.insert(name, keyframes.into());
+        }
+
+        // Transform constraint timelines
+        let count = self.read_varint()?;
+        for _ in 0..count {
+            let index = self.read_varint()?;
+            let name = lookup(&self.transform, index, "transform constraint")?;
+            let mut keyframes = vec![];
+
+            let frame_count = self.read_varint()?;
+            for frame in 0..frame_count {
+                keyframes.push(AnimationTransform {
+                    time: self.read_f32()?,
+                    rotate_mix: self.read_f32()?,
+                    translate_mix: self.read_f32()?,
+                    scale_mix: self.read_f32()?,
+                    shear_mix: self.read_f32()?,
+                    curve: self.read_curve(frame, frame_count)?,
+                });
+            }
+
+            animation.transform.insert(name, keyframes.into());
+        }
+
+        // Path constraint timelines
+        let count = self.read_varint()?;
+        for _ in 0..count {
+            let index = self.read_varint()?;
+            let name = lookup(&self.path, index, "path constraint")?;
+            let mut timelines = AnimationPath::default();
+
+            let timeline_count = self.read_varint()?;
+            for _ in 0..timeline_count {
+                let timeline_type = self.read_u8()?;
+                let frame_count = self.read_varint()?;
+                for frame in 0..frame_count {
+                    let time = self.read_f32()?;
+                    match timeline_type {
+                        PATH_POSITION => {
+                            let position = self.read_f32()?;
+                            let curve = self.read_curve(frame, frame_count)?;
+                            timelines.position.push(PathPositionKeyframe {
+                                time,
+                                position,
+                                curve,
+                            });
+                        }
+                        PATH_SPACING => {
+                            let spacing = self.read_f32()?;
+                            let curve = self.read_curve(frame, frame_count)?;
+                            timelines.spacing.push(PathSpacingKeyframe {
+                                time,
+                                spacing,
+                                curve,
+                            });
+                        }
+                        PATH_MIX => {
+                            let rotate_mix = self.read_f32()?;
+                            let translate_mix = self.read_f32()?;
+                            let curve = self.read_curve(frame, frame_count)?;
+                            timelines.mix.push(PathMixKeyframe {
+                                time,
+                                rotate_mix,
+                                translate_mix,
+                                curve,
+                            });
+                        }
+                        v => bail!("invalid path timeline type {}", v),
+                    }
+                }
+            }
+
+            animation.path.insert(name, timelines.into());
+        }
+
+        // Deform timelines
+        let count = self.read_varint()?;
+        for _ in 0..count {
+            let skin_index = self.read_varint()?;
+            let skin = lookup(&self.skins, skin_index, "skin")?;
+
+            let slot_count = self.read_varint()?;
+            for _ in 0..slot_count {
+                let slot_index = self.read_varint()?;
+                let slot = lookup(&self.slots, slot_index, "slot")?;
+
+                let attachment_count = self.read_varint()?;
+                for _ in 0..attachment_count {
+                    let attachment = self.read_string_ref()?.unwrap_or_default();
+                    if !self.vertex_attachments.contains(&(
+                        skin_index,
+                        slot_index,
+                        attachment.clone(),
+                    )) {
+                        bail!(
+                            "deform of unknown vertex attachment \"{}\" in slot \"{}\"",
+                            attachment,
+                            slot
+                        );
+                    }
+
+                    let mut keyframes = vec![];
+                    let frame_count = self.read_varint()?;
+                    for frame in 0..frame_count {
+                        let time = self.read_f32()?;
+                        let mut offset = 0;
+                        let mut vertices = vec![];
+
+                        let end = self.read_varint()?;
+                        if end != 0 {
+                            offset = self.read_varint()?;
+                            vertices = self.read_f32_array(end)?;
+                        }
+
+                        keyframes.push(AnimationDeform {
+                            time,
+                            vertices,
+                            offset: offset as i32,
+                            curve: self.read_curve(frame, frame_count)?,
+                        });
+                    }
+
+                    animation
+                        .deform
+                        .entry(skin.clone())
+                        .or_default()
+                        .entry(slot.clone())
+                        .or_default()
+                        .insert(attachment, keyframes);
+                }
+            }
+        }
+
+        // Draw order timeline
+        let count = self.read_varint()?;
+        for _ in 0..count {
+            let time = self.read_f32()?;
+            let mut offsets = vec![];
+
+            let offset_count = self.read_varint()?;
+            for _ in 0..offset_count {
+                let slot = self.slot_name()?;
+                // Negative offsets are encoded as a wrapped unsigned int
+                let offset = self.read_varint()? as u32 as i32;
+                offsets.push(DrawOrderOffset {
+                    slot,
+                    offset,
+                    target: None,
+                });
+            }
+
+            animation
+                .draw_order
+                .push(AnimationDrawOrder { time, offsets });
+        }
+
+        // Event timeline
+        let count = self.read_varint()?;
+        for _ in 0..count {
+            let time = self.read_f32()?;
+            let index = self.read_varint()?;
+            let (name, data, has_audio) = self
+                .events
+                .get(index)
+                .ok_or_else(|| anyhow!("invalid event index {}", index))?;
+            let (name, data, has_audio) = (name.clone(), data.clone(), *has_audio);
+
+            // Keys always carry their values, the ones matching the event data
+            // are left out just like in the json format
+            let int = self.read_varint_signed()? as i64;
+            let float = self.read_f32()?;
+            let mut event = AnimationEvent {
+                name: Some(name),
+                time,
+                int: Some(int as isize).filter(|_| int != data.int),
+                float: Some(float).filter(|float| *float != data.float),
+                ..Default::default()
+            };
+            if self.read_bool()? {
+                event.string = self.read_string()?;
+            }
+            if has_audio {
+                let volume = self.read_f32()?;
+                let balance = self.read_f32()?;
+                event.volume = Some(volume).filter(|volume| *volume != data.volume);
+                event.balance = Some(balance).filter(|balance| *balance != data.balance);
+            }
+
+            animation.events.push(event);
+        }
+
+        Ok(animation)
+    }
+
+    fn read_curve(&mut self, frame: usize, frame_count: usize) -> Result<Interpolation, Error> {
+        // Last frame doesn't have a curve
+        if frame + 1 >= frame_count {
+            return Ok(Interpolation::Linear);
+        }
+
+        match self.read_u8()? {
+            CURVE_LINEAR => Ok(Interpolation::Linear),
+            CURVE_STEPPED => Ok(Interpolation::Stepped),
+            CURVE_BEZIER => Ok(Interpolation::BezierCurve([
+                self.read_f32()?,
+                self.read_f32()?,
+                self.read_f32()?,
+                self.read_f32()?,
+            ])),
+            v => bail!("invalid curve type {}", v),
+        }
+    }
+
+    ///////////////////////////////////////////////////////////////////////////
+
+    fn bone_name(&mut self) -> Result<String, Error> {
+        let index = self.read_varint()?;
+        lookup(&self.bones, index, "bone")
+    }
+
+    fn slot_name(&mut self) -> Result<String, Error> {
+        let index = self.read_varint()?;
+        lookup(&self.slots, index, "slot")
+    }
+
+    fn read_bone_names(&mut self) -> Result<Vec<String>, Error> {
+        let count = self.read_varint()?;
+        let mut bones = Vec::with_capacity(self.capacity(count, 1));
+        for _ in 0..count {
+            bones.push(self.bone_name()?);
+        }
+        Ok(bones)
+    }
+
+    /// Capacity for `len` items read from the file, each taking at least `size` bytes;
+    /// Lengths aren't trusted, the capacity never exceeds what's left of the buffer
+    fn capacity(&self, len: usize, size: usize) -> usize {
+        len.min((self.buffer.len() - self.cursor) / size)
+    }
+
+    fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        let end = self.cursor + len;
+        if end > self.buffer.len() {
+            bail!("unexpected end of file at byte {}", self.buffer.len());
+        }
+        let bytes = &self.buffer[self.cursor..end];
+        self.cursor = end;
+        Ok(bytes)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, Error> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    fn read_bool(&mut self) -> Result<bool, Error> {
+        Ok(self.read_u8()? != 0)
+    }
+
+    fn read_i32(&mut self) -> Result<i32, Error> {
+        let bytes = self.read_bytes(4)?;
+        Ok(i32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn read_f32(&mut self) -> Result<f32, Error> {
+        Ok(f32::from_bits(self.read_i32()? as u32))
+    }
+
+    /// Variable length positive int
+    fn read_varint(&mut self) -> Result<usize, Error> {
+        let mut result = 0u32;
+        for i in 0..5 {
+            let b = self.read_u8()? as u32;
+            if i == 4 {
+                result |= b << 28;
+                break;
+            }
+            result |= (b & 0x7F) << (i * 7);
+            if b & 0x80 == 0 {
+                break;
+            }
+        }
+        Ok(result as usize)
+    }
+
+    /// Variable length zig-zag encoded int
+    fn read_varint_signed(&mut self) -> Result<i32, Error> {
+        let value = self.read_varint()? as u32;
+        Ok(((value >> 1) as i32) ^ -((value & 1) as i32))
+    }
+
+    fn read_string(&mut self) -> Result<Option<String>, Error> {
+        match self.read_varint()? {
+            0 => Ok(None),
+            1 => Ok(Some(String::new())),
+            len => {
+                let bytes = self.read_bytes(len - 1)?;
+                Ok(Some(String::from_utf8(bytes.to_vec())?))
+            }
+        }
+    }
+
+    fn read_string_ref(&mut self) -> Result<Option<String>, Error> {
+        match self.read_varint()? {
+            0 => Ok(None),
+            index => Ok(Some(lookup(&self.strings, index - 1, "string")?)),
+        }
+    }
+
+    /// Reads a `rgba8888` color into a hex string
+    fn read_color(&mut self) -> Result<String, Error> {
+        Ok(format!("{:08x}", self.read_i32()? as u32))
+    }
+
+    fn read_f32_array(&mut self, len: usize) -> Result<Vec<f32>, Error> {
+        let mut values = Vec::with_capacity(self.capacity(len, 4));
+        for _ in 0..len {
+            values.push(self.read_f32()?);
+        }
+        Ok(values)
+    }
+
+    fn read_u16_array(&mut self) -> Result<Vec<u32>, Error> {
+        let len = self.read_varint()?;
+        let mut values = Vec::with_capacity(self.capacity(len, 2));
+        for _ in 0..len {
+            let bytes = self.read_bytes(2)?;
+            values.push(u16::from_be_bytes([bytes[0], bytes[1]]) as u32);
+        }
+        Ok(values)
+    }
+}
+
+fn lookup(names: &[String], index: usize, kind: &str) -> Result<String, Error> {
+    names
+        .get(index)
+        .cloned()
+        .ok_or_else(|| anyhow!("invalid {} index {}", kind, index))
+}
@@ -0,0 +1,552 @@
+//! Migrates json files exported by the editor versions `4.0.x`, `4.1.x` and `4.2.x` into the same
+//! data model used by the `3.8.x` format, see http://esotericsoftware.com/spine-changelog
+//!
+//! The migration works over the raw json before it's deserialized, features without a `3.8.x`
+//! counterpart, like the physics constraints added in `4.2`, are dropped.
+
+use std::{cmp::Ordering, collections::HashMap};
+
+use anyhow::{bail, Error};
+use serde_json::{Map, Value};
+
+/// Json format version, given by the editor version found in `skeleton.spine`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum SpineVersion {
+    V3_8,
+    V4_0,
+    V4_1,
+    V4_2,
+}
+
+impl SpineVersion {
+    pub fn parse(version: &str) -> Result<Self, Error> {
+        let mut numbers = version.split('.');
+        match (numbers.next(), numbers.next()) {
+            (Some("3"), Some("8")) => Ok(SpineVersion::V3_8),
+            (Some("4"), Some("0")) => Ok(SpineVersion::V4_0),
+            (Some("4"), Some("1")) => Ok(SpineVersion::V4_1),
+            (Some("4"), Some("2")) => Ok(SpineVersion::V4_2),
+            _ => bail!(
+                "unsupported spine version \"{}\", expected: \"3.8.x\", \"4.0.x\", \"4.1.x\" or \"4.2.x\"",
+                version
+            ),
+        }
+    }
+}
+
+/// Migrates the json `spine` file to the `3.8.x` format, fails when the version isn't supported
+pub(crate) fn migrate(spine: &mut Value) -> Result<(), Error> {
+    let version = match spine["skeleton"]["spine"].as_str() {
+        Some(version) => SpineVersion::parse(version)?,
+        None => bail!("missing spine version, expected: `skeleton.spine`"),
+    };
+
+    if version >= SpineVersion::V4_0 {
+        if let Some(spine) = spine.as_object_mut() {
+            migrate_4(spine);
+        }
+    }
+
+    Ok(())
+}
+
+fn migrate_4(spine: &mut Map<String, Value>) {
+    // Physics constraints, since 4.2
+    spine.remove("physics");
+    if let Some(skeleton) = spine.get_mut("skeleton").and_then(Value::as_object_mut) {
+        skeleton.remove("referenceScale");
+    }
+
+    for bone in items(spine.get_mut("bones")) {
+        // Renamed in 4.2
+        rename(bone, "inherit", "transform");
+        bone.remove("icon");
+        bone.remove("visible");
+    }
+
+    let mut setup_colors = HashMap::new();
+    for slot in items(spine.get_mut("slots")) {
+        slot.remove("visible");
+        if let Some(name) = slot.get("name").and_then(Value::as_str) {
+            let mut color = color_channels(string(slot.get("color"), "ffffffff"));
+            color.resize(4, 1.0);
+            setup_colors.insert(name.to_string(), color);
+        }
+    }
+
+    for constraint in items(spine.get_mut("transform")) {
+        migrate_mixes(constraint);
+    }
+    for constraint in items(spine.get_mut("path")) {
+        migrate_mixes(constraint);
+    }
+
+    for animation in values(spine.get_mut("animations")) {
+        migrate_animation(animation, &setup_colors);
+    }
+}
+
+fn migrate_animation(animation: &mut Map<String, Value>, setup_colors: &HashMap<String, Vec<f64>>) {
+    animation.remove("physics");
+
+    if let Some(slots) = animation.get_mut("slots").and_then(Value::as_object_mut) {
+        for (slot, timelines) in slots.iter_mut() {
+            if let Some(timelines) = timelines.as_object_mut() {
+                let white = vec![1.0; 4];
+                migrate_slot_timelines(timelines, setup_colors.get(slot).unwrap_or(&white));
+            }
+        }
+    }
+
+    for timelines in values(animation.get_mut("bones")) {
+        migrate_bone_timelines(timelines);
+    }
+
+    for keyframes in animation
+        .get_mut("ik")
+        .and_then(Value::as_object_mut)
+        .into_iter()
+        .flat_map(|constraints| constraints.values_mut())
+    {
+        normalize_curves(keyframes, |k| {
+            vec![number(k, "mix", 1.0), number(k, "softness", 0.0)]
+        });
+    }
+
+    for keyframes in animation
+        .get_mut("transform")
+        .and_then(Value::as_object_mut)
+        .into_iter()
+        .flat_map(|constraints| constraints.values_mut())
+    {
+        normalize_curves(keyframes, |k| {
+            [
+                "mixRotate",
+                "mixX",
+                "mixY",
+                "mixScaleX",
+                "mixScaleY",
+                "mixShearY",
+            ]
+            .iter()
+            .map(|mix| number(k, mix, 1.0))
+            .collect()
+        });
+        for keyframe in items(Some(keyframes)) {
+            migrate_mixes(keyframe);
+        }
+    }
+
+    for timelines in values(animation.get_mut("path")) {
+        for timeline in &["position", "spacing"] {
+            if let Some(keyframes) = timelines.get_mut(*timeline) {
+                normalize_curves(keyframes, |k| vec![number(k, "value", 0.0)]);
+                for keyframe in items(Some(keyframes)) {
+                    rename(keyframe, "value", timeline);
+                }
+            }
+        }
+        if let Some(keyframes) = timelines.get_mut("mix") {
+            normalize_curves(keyframes, |k| {
+                ["mixRotate", "mixX", "mixY"]
+                    .iter()
+                    .map(|mix| number(k, mix, 1.0))
+                    .collect()
+            });
+            for keyframe in items(Some(keyframes)) {
+                migrate_mixes(keyframe);
+            }
+        }
+    }
+
+    // Deform timelines are found along side the sequence timelines since 4.1
+    let mut deforms = vec![];
+    if let Some(skins) = animation
+        .get_mut("attachments")
+        .and_then(Value::as_object_mut)
+    {
+        for (skin, slots) in skins.iter_mut() {
+            for (slot, attachments) in slots.as_object_mut().into_iter().flatten() {
+                for (name, timelines) in attachments.as_object_mut().into_iter().flatten() {
+                    if let Some(deform) = timelines.as_object_mut().and_then(|t| t.remove("deform"))
+                    {
+                        deforms.push((skin.clone(), slot.clone(), name.clone(), deform));
+                    }
+                }
+            }
+        }
+    }
+    for (skin, slot, name, deform) in deforms {
+        let skins = object_entry(animation, "deform");
+        let slots = object_entry(skins, &skin);
+        object_entry(slots, &slot).insert(name, deform);
+    }
+
+    for slots in values(animation.get_mut("deform")) {
+        for attachments in slots.values_mut().filter_map(Value::as_object_mut) {
+            for keyframes in attachments.values_mut() {
+                // Deform keyframes are blended from 0 to 1
+                normalize_curves(keyframes, |_| vec![]);
+            }
+        }
+    }
+}
+
+fn migrate_slot_timelines(timelines: &mut Map<String, Value>, setup: &[f64]) {
+    if let Some(mut keyframes) = timelines.remove("rgba") {
+        normalize_curves(&mut keyframes, |k| {
+            color_channels(string(k.get("color"), "ffffffff"))
+        });
+        timelines.insert("color".to_string(), keyframes);
+    }
+
+    // Color and alpha keyed separately
+    let rgb = timelines.remove("rgb");
+    let alpha = timelines.remove("alpha");
+    if rgb.is_some() || alpha.is_some() {
+        let rgb = split_keys(rgb, |k| color_channels(string(k.get("color"), "ffffff")));
+        let alpha = split_keys(alpha, |k| vec![number(k, "value", 1.0)]);
+        let keyframes = merge(&[rgb, alpha], &[&setup[..3], &setup[3..]])
+            .into_iter()
+            .map(|key| {
+                key.into_keyframe(|k, values| {
+                    k.insert("color".into(), color_hex(values).into());
+                })
+            })
+            .collect();
+        timelines.insert("color".to_string(), Value::Array(keyframes));
+    }
+
+    if let Some(mut keyframes) = timelines.remove("rgba2") {
+        normalize_curves(&mut keyframes, |k| {
+            let mut channels = color_channels(string(k.get("light"), "ffffffff"));
+            channels.extend(color_channels(string(k.get("dark"), "000000")));
+            channels
+        });
+        timelines.insert("twoColor".to_string(), keyframes);
+    }
+
+    if let Some(mut keyframes) = timelines.remove("rgb2") {
+        normalize_curves(&mut keyframes, |k| {
+            let mut channels = color_channels(string(k.get("light"), "ffffff"));
+            channels.extend(color_channels(string(k.get("dark"), "000000")));
+            channels
+        });
+        // Light alpha isn't keyed, keeps the setup alpha
+        for keyframe in items(Some(&mut keyframes)) {
+            let light = format!(
+                "{}{}",
+                string(keyframe.get("light"), "ffffff"),
+                color_hex(&setup[3..])
+            );
+            keyframe.insert("light".to_string(), light.into());
+        }
+        timelines.insert("twoColor".to_string(), keyframes);
+    }
+}
+
+fn migrate_bone_timelines(timelines: &mut Map<String, Value>) {
+    if let Some(keyframes) = timelines.get_mut("rotate") {
+        normalize_curves(keyframes, |k| vec![number(k, "value", 0.0)]);
+        for keyframe in items(Some(keyframes)) {
+            rename(keyframe, "value", "angle");
+        }
+    }
+
+    for &(timeline, default) in &[("translate", 0.0), ("scale", 1.0), ("shear", 0.0)] {
+        if let Some(keyframes) = timelines.get_mut(timeline) {
+            normalize_curves(keyframes, |k| {
+                vec![number(k, "x", default), number(k, "y", default)]
+            });
+        }
+
+        // Each axis keyed separately
+        let x = timelines.remove(&format!("{}x", timeline));
+        let y = timelines.remove(&format!("{}y", timeline));
+        if x.is_some() || y.is_some() {
+            let x = split_keys(x, |k| vec![number(k, "value", default)]);
+            let y = split_keys(y, |k| vec![number(k, "value", default)]);
+            let keyframes = merge(&[x, y], &[&[default], &[default]])
+                .into_iter()
+                .map(|key| {
+                    key.into_keyframe(|k, values| {
+                        k.insert("x".into(), values[0].into());
+                        k.insert("y".into(), values[1].into());
+                    })
+                })
+                .collect();
+            timelines.insert(timeline.to_string(), Value::Array(keyframes));
+        }
+    }
+}
+
+/// Constraint mixes are given per axis since 4.0, only the x and scale x mixes are kept
+fn migrate_mixes(constraint: &mut Map<String, Value>) {
+    rename(constraint, "mixRotate", "rotateMix");
+    rename(constraint, "mixX", "translateMix");
+    rename(constraint, "mixScaleX", "scaleMix");
+    rename(constraint, "mixShearY", "shearMix");
+    constraint.remove("mixY");
+    constraint.remove("mixScaleY");
+}
+
+/// Since 4.0 the bezier `curve` is an array with 4 values for each keyframe channel, given in the
+/// time and value space of the keyframe. The curve of the channel that changes the most is
+/// normalized into the `curve`, `c2`, `c3` and `c4` used by 3.8, `values` gives the channels of a keyframe
+fn normalize_curves(keyframes: &mut Value, values: impl Fn(&Value) -> Vec<f64>) {
+    let keyframes = match keyframes.as_array_mut() {
+        Some(keyframes) => keyframes,
+        None => return,
+    };
+
+    let samples = keyframes
+        .iter()
+        .map(|k| (number(k, "time", 0.0), values(k)))
+        .collect::<Vec<_>>();
+
+    for (i, keyframe) in keyframes.iter_mut().enumerate() {
+        let keyframe = match keyframe.as_object_mut() {
+            Some(keyframe) => keyframe,
+            None => continue,
+        };
+        let curve = match keyframe.get("curve") {
+            Some(Value::Array(curve)) => curve
+                .iter()
+                .map(|v| v.as_f64().unwrap_or(0.0))
+                .collect::<Vec<_>>(),
+            _ => continue,
+        };
+        keyframe.remove("curve");
+
+        let ((t0, v0), (t1, v1)) = match (samples.get(i), samples.get(i + 1)) {
+            (Some(a), Some(b)) => (a, b),
+            _ => continue,
+        };
+
+        // Keyframes without channels are blended from 0 to 1
+        let (channel, a, b) = (0..v0.len().min(v1.len()))
+            .filter(|c| curve.len() >= c * 4 + 4)
+            .map(|c| (c, v0[c], v1[c]))
+            .max_by(|x, y| {
+                (x.2 - x.1)
+                    .abs()
+                    .partial_cmp(&(y.2 - y.1).abs())
+                    .unwrap_or(Ordering::Equal)
+            })
+            .unwrap_or((0, 0.0, 1.0));
+        if curve.len() < channel * 4 + 4 {
+            continue;
+        }
+        let c = &curve[channel * 4..channel * 4 + 4];
+
+        let dt = t1 - t0;
+        let (cx1, cx2) = if dt > 0.0 {
+            ((c[0] - t0) / dt, (c[2] - t0) / dt)
+        } else {
+            (0.0, 1.0)
+        };
+        let dv = b - a;
+        let (cy1, cy2) = if dv.abs() > 1e-6 {
+            ((c[1] - a) / dv, (c[3] - a) / dv)
+        } else {
+            (cx1, cx2)
+        };
+
+        keyframe.insert("curve".to_string(), cx1.into());
+        keyframe.insert("c2".to_string(), cy1.into());
+        keyframe.insert("c3".to_string(), cx2.into());
+        keyframe.insert("c4".to_string(), cy2.into());
+    }
+}
+
+/// Keyframe of a timeline keyed per channel, see [`merge`]
+struct Key {
+    time: f64,
+    values: Vec<f64>,
+    curve: Map<String, Value>,
+}
+
+impl Key {
+    fn stepped(&self) -> bool {
+        self.curve.get("curve").and_then(Value::as_str) == Some("stepped")
+    }
+
+    fn into_keyframe(self, fields: impl FnOnce(&mut Map<String, Value>, &[f64])) -> Value {
+        let mut keyframe = self.curve;
+        keyframe.insert("time".to_string(), self.time.into());
+        fields(&mut keyframe, &self.values);
+        Value::Object(keyframe)
+    }
+}
+
+/// Keys of a timeline keyed per channel, the curves are normalized
+fn split_keys(keyframes: Option<Value>, values: impl Fn(&Value) -> Vec<f64>) -> Vec<Key> {
+    let mut keyframes = keyframes.unwrap_or(Value::Null);
+    normalize_curves(&mut keyframes, &values);
+    keyframes
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|k| Key {
+            time: number(k, "time", 0.0),
+            values: values(k),
+            curve: ["curve", "c2", "c3", "c4"]
+                .iter()
+                .filter_map(|name| k.get(*name).map(|v| (name.to_string(), v.clone())))
+                .collect(),
+        })
+        .collect()
+}
+
+/// Time before a channel jumps to a new value where the merged timeline holds, see [`merge`]
+const HOLD_TIME: f64 = 1e-4;
+
+/// Merges the `timelines` of each channel into a single timeline keyed at every time found in any
+/// of them, channels not keyed at some time are linearly interpolated or use their setup `defaults`.
+/// Curves are kept only when the merged keyframe don't split the original curve;
+///
+/// Channels keep their setup value until their first key and stepped keys keep their value until
+/// the next key, the merged timeline jumps to the new value with a stepped key right before it
+fn merge(timelines: &[Vec<Key>], defaults: &[&[f64]]) -> Vec<Key> {
+    let sort = |times: &mut Vec<f64>| {
+        times.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+        times.dedup();
+    };
+
+    let mut keyed = timelines
+        .iter()
+        .flatten()
+        .map(|key| key.time)
+        .collect::<Vec<_>>();
+    sort(&mut keyed);
+
+    let jumps = timelines.iter().flat_map(|keys| {
+        keys.iter()
+            .enumerate()
+            .filter(move |(index, _)| index.checked_sub(1).map_or(true, |i| keys[i].stepped()))
+            .map(|(_, key)| key.time)
+    });
+    let mut times = jumps
+        .map(|time| (time - HOLD_TIME, time))
+        .filter(|&(hold, time)| {
+            // Only needed when the merged timeline is keyed before the jump
+            keyed.iter().any(|&t| t < hold) && !keyed.iter().any(|&t| t >= hold && t < time)
+        })
+        .map(|(hold, _)| hold)
+        .chain(keyed.iter().copied())
+        .collect::<Vec<_>>();
+    sort(&mut times);
+
+    times
+        .iter()
+        .enumerate()
+        .map(|(i, &time)| {
+            let mut values = vec![];
+            let mut curve = Map::new();
+            if !keyed.contains(&time) {
+                curve.insert("curve".to_string(), "stepped".into());
+            }
+
+            // Holding keys don't split the curves
+            let next = times[i + 1..].iter().copied().find(|t| keyed.contains(t));
+            for (keys, default) in timelines.iter().zip(defaults) {
+                values.extend(sample(keys, time, default));
+                if curve.is_empty() {
+                    if let Some(index) = keys.iter().position(|key| key.time == time) {
+                        let key = &keys[index];
+                        if !key.stepped() && keys.get(index + 1).map(|key| key.time) == next {
+                            curve = key.curve.clone();
+                        }
+                    }
+                }
+            }
+            Key {
+                time,
+                values,
+                curve,
+            }
+        })
+        .collect()
+}
+
+fn sample(keys: &[Key], time: f64, default: &[f64]) -> Vec<f64> {
+    let index = match keys.iter().rposition(|key| key.time <= time) {
+        Some(index) => index,
+        // Setup pose before the first key
+        None => return default.to_vec(),
+    };
+
+    let key = &keys[index];
+    match keys.get(index + 1) {
+        Some(next) if !key.stepped() && next.time > key.time => {
+            let t = (time - key.time) / (next.time - key.time);
+            key.values
+                .iter()
+                .zip(next.values.iter())
+                .map(|(a, b)| a + (b - a) * t)
+                .collect()
+        }
+        _ => key.values.clone(),
+    }
+}
+
+/// Objects of a json array
+fn items(value: Option<&mut Value>) -> impl Iterator<Item = &mut Map<String, Value>> {
+    value
+        .and_then(Value::as_array_mut)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_object_mut)
+}
+
+/// Objects found in the values of a json object
+fn values(value: Option<&mut Value>) -> impl Iterator<Item = &mut Map<String, Value>> {
+    value
+        .and_then(Value::as_object_mut)
+        .into_iter()
+        .flat_map(|map| map.values_mut())
+        .filter_map(Value::as_object_mut)
+}
+
+/// Object found in the `key`, inserted when missing
+fn object_entry<'a>(map: &'a mut Map<String, Value>, key: &str) -> &'a mut Map<String, Value> {
+    let value = map
+        .entry(key.to_string())
+        .or_insert_with(|| Value::Object(Map::new()));
+    if !value.is_object() {
+        *value = Value::Object(Map::new());
+    }
+    value.as_object_mut().unwrap()
+}
+
+fn rename(map: &mut Map<String, Value>, from: &str, to: &str) {
+    if let Some(value) = map.remove(from) {
+        map.insert(to.to_string(), value);
+    }
+}
+
+fn number(value: &Value, key: &str, default: f64) -> f64 {
+    value.get(key).and_then(Value::as_f64).unwrap_or(default)
+}
+
+fn string<'a>(value: Option<&'a Value>, default: &'a str) -> &'a str {
+    value.and_then(Value::as_str).unwrap_or(default)
+}
+
+/// Channels of a `RRGGBB` or `RRGGBBAA` color in the range `[0, 1]`
+fn color_channels(hex: &str) -> Vec<f64> {
+    (0..hex.len() / 2)
+        .map(|i| {
+            hex.get(i * 2..i * 2 + 2)
+                .and_then(|channel| u8::from_str_radix(channel, 16).ok())
+                .map_or(1.0, |channel| channel as f64 / 255.0)
+        })
+        .collect()
+}
+
+fn color_hex(channels: &[f64]) -> String {
+    channels
+        .iter()
+        .map(|channel| format!("{:02x}", (channel.clamp(0.0, 1.0) * 255.0).round() as u8))
+        .collect()
+}
@@ -4,6 +4,8 @@
 //! and (binary)[http://pt.esotericsoftware.com/spine-binary-format]
 
 pub mod atlas;
+mod binary;
+mod migrate;
 pub mod spine;
 
 pub use atlas::Atlas;
@@ -14,7 +16,9 @@ mod tests {
     use std::fs::File;
 
     use super::*;
+    use crate::animation::sampling::{lerp, sample};
     use anyhow::{Context, Error};
+    use serde_json::Value;
     use walkdir::WalkDir;
 
     #[test]
@@ -55,4 +59,540 @@ mod tests {
             }
         }
     }
+
+    /// Compares two json values, the json format only keep a few decimal places so numbers are approximated
+    fn assert_equivalent(a: &Value, b: &Value, path: &str) {
+        match (a, b) {
+            (Value::Number(a), Value::Number(b)) => {
+                let a = a.as_f64().unwrap();
+                let b = b.as_f64().unwrap();
+                assert!(
+                    (a - b).abs() <= 0.01 + 1e-4 * a.abs().max(b.abs()),
+                    "{}: {} != {}",
+                    path,
+                    a,
+                    b
+                );
+            }
+            (Value::String(a), Value::String(b)) => {
+                assert!(a.eq_ignore_ascii_case(b), "{}: {:?} != {:?}", path, a, b);
+            }
+            (Value::Array(a), Value::Array(b)) => {
+                assert_eq!(a.len(), b.len(), "{}: length mismatch", path);
+                for (i, (a, b)) in a.iter().zip(b.iter()).enumerate() {
+                    assert_equivalent(a, b, &format!("{}[{}]", path, i));
+                }
+            }
+            (Value::Object(a), Value::Object(b)) => {
+                let keys_a = a.keys().collect::<Vec<_>>();
+                let keys_b = b.keys().collect::<Vec<_>>();
+                assert_eq!(keys_a, keys_b, "{}: keys mismatch", path);
+                for (key, a) in a.iter() {
+                    assert_equivalent(a, &b[key], &format!("{}.{}", path, key));
+                }
+            }
+            (a, b) => assert_eq!(a, b, "{}", path),
+        }
+    }
+
+    /// Some of the sample `.skel` and `.json` files don't agree on which keyframes are stepped
+    fn remove_stepped(value: &mut Value) {
+        match value {
+            Value::Array(values) => values.iter_mut().for_each(remove_stepped),
+            Value::Object(map) => {
+                if map.get("curve").map_or(false, |curve| curve == "stepped") {
+                    map.remove("curve");
+                }
+                map.values_mut().for_each(remove_stepped);
+            }
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn test_binary_matches_json() {
+        for e in WalkDir::new("assets/").min_depth(2).max_depth(2) {
+            if let Ok(e) = e {
+                if e.path().extension().map_or("", |os| os.to_str().unwrap()) != "skel" {
+                    continue;
+                }
+
+                let path = e.path().to_str().unwrap();
+                let binary = File::open(&path)
+                    .map_err(Error::from)
+                    .and_then(|file| Spine::parse_binary(file))
+                    .with_context(|| format!("spine file \"{}\"", &path))
+                    .unwrap();
+
+                let json_path = e.path().with_extension("json");
+                let json = Spine::parse(File::open(&json_path).unwrap()).unwrap();
+
+                let mut binary = serde_json::to_value(&binary).unwrap();
+                let mut json = serde_json::to_value(&json).unwrap();
+
+                // Each file have it's own hash
+                binary["skeleton"]["hash"] = Value::Null;
+                json["skeleton"]["hash"] = Value::Null;
+
+                // Skins without attachments aren't exported in binary
+                json["skins"]
+                    .as_array_mut()
+                    .unwrap()
+                    .retain(|skin| !skin["attachments"].as_object().unwrap().is_empty());
+
+                // Deform values close to zero are trimmed differently by each format
+                for spine in &mut [&mut binary, &mut json] {
+                    for animation in spine["animations"].as_object_mut().unwrap().values_mut() {
+                        for skin in animation["deform"].as_object_mut().unwrap().values_mut() {
+                            for slot in skin.as_object_mut().unwrap().values_mut() {
+                                for keyframes in slot.as_object_mut().unwrap().values_mut() {
+                                    for keyframe in keyframes.as_array_mut().unwrap() {
+                                        let offset = keyframe["offset"].as_u64().unwrap() as usize;
+                                        let mut vertices = vec![0.0; offset];
+                                        vertices.extend(
+                                            keyframe["vertices"]
+                                                .as_array()
+                                                .unwrap()
+                                                .iter()
+                                                .map(|v| v.as_f64().unwrap()),
+                                        );
+                                        while vertices.last().map_or(false, |v| v.abs() < 0.001) {
+                                            vertices.pop();
+                                        }
+                                        keyframe["offset"] = 0.into();
+                                        keyframe["vertices"] = vertices.into();
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+
+                remove_stepped(&mut binary);
+                remove_stepped(&mut json);
+
+                assert_equivalent(&binary, &json, path);
+            }
+        }
+    }
+
+    #[test]
+    fn test_binary_corrupted() {
+        let bytes = std::fs::read("assets/spineboy/spineboy-pro.skel").unwrap();
+
+        // Lengths read from a corrupted file can be huge, they fail without allocating
+        for i in (0..bytes.len() - 5).step_by(101) {
+            let mut corrupted = bytes.clone();
+            corrupted[i..i + 5].copy_from_slice(&[0xff, 0xff, 0xff, 0xff, 0x0f]);
+            let _ = Spine::parse_binary(&corrupted[..]);
+        }
+
+        assert!(Spine::parse_binary(&bytes[..bytes.len() / 2]).is_err());
+    }
+
+    #[test]
+    fn test_example_assets() {
+        // Loaded by the `SpineImpoter` in the examples
+        let spine = Spine::parse(File::open("assets/hero/hero.spine_json").unwrap()).unwrap();
+        assert!(spine.animations.contains_key("idle"));
+
+        let atlas = Atlas::parse(File::open("assets/hero/hero.spine_atlas").unwrap()).unwrap();
+        for skin in &spine.skins {
+            for (slot, attachments) in &skin.attachments {
+                for (name, attachment) in attachments {
+                    let path = match attachment {
+                        spine::SkinAttachment::Region { path, .. }
+                        | spine::SkinAttachment::Mesh { path, .. } => path.as_ref().unwrap_or(name),
+                        _ => continue,
+                    };
+                    assert!(
+                        atlas.regions.iter().any(|region| &region.name == path),
+                        "region \"{}\" of slot \"{}\" not found",
+                        path,
+                        slot
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_pma_atlas() {
+        // Spine 3.8 atlases don't have the `pma` key
+        let atlas = Atlas::parse(File::open("assets/hero/hero-pma.atlas").unwrap()).unwrap();
+        assert!(!atlas.pages[0].pma);
+        assert!(atlas.pages[0].premultiplied_alpha());
+
+        let atlas = Atlas::parse(File::open("assets/hero/hero.atlas").unwrap()).unwrap();
+        assert!(!atlas.pages[0].premultiplied_alpha());
+
+        // Every page of multi page atlases
+        let atlas = Atlas::parse(File::open("assets/dragon/dragon-pma.atlas").unwrap()).unwrap();
+        assert_eq!(atlas.pages.len(), 2);
+        assert!(atlas
+            .pages
+            .iter()
+            .all(atlas::AtlasPage::premultiplied_alpha));
+    }
+
+    #[test]
+    fn test_resolve_timelines() {
+        for e in WalkDir::new("assets/").min_depth(2).max_depth(2) {
+            let e = e.unwrap();
+            let path = e.path().to_str().unwrap();
+            let spine = match e.path().extension().map_or("", |os| os.to_str().unwrap()) {
+                "json" => Spine::parse(File::open(path).unwrap()).unwrap(),
+                "skel" => Spine::parse_binary(File::open(path).unwrap()).unwrap(),
+                _ => continue,
+            };
+
+            for animation in spine.animations.values() {
+                for (name, timeline) in &animation.bones {
+                    assert_eq!(&spine.bones[timeline.target.unwrap()].name, name);
+                }
+                for (name, timeline) in &animation.ik {
+                    assert_eq!(&spine.ik[timeline.target.unwrap()].name, name);
+                }
+                for (name, timeline) in &animation.transform {
+                    assert_eq!(&spine.transform[timeline.target.unwrap()].name, name);
+                }
+                for (name, timeline) in &animation.path {
+                    assert_eq!(&spine.path[timeline.target.unwrap()].name, name);
+                }
+                for (name, timeline) in &animation.slots {
+                    assert_eq!(&spine.slots[timeline.target.unwrap()].name, name);
+                }
+                for offset in animation.draw_order.iter().flat_map(|k| k.offsets.iter()) {
+                    assert_eq!(spine.slots[offset.target.unwrap()].name, offset.slot);
+                }
+                for (skin, slots) in &animation.deform {
+                    assert_eq!(&spine.skins[slots.target.unwrap()].name, skin);
+                    for (name, timelines) in slots.iter() {
+                        assert_eq!(&spine.slots[timelines.target.unwrap()].name, name);
+                    }
+                }
+                for (skin, slots) in &animation.attachments {
+                    assert_eq!(&spine.skins[slots.target.unwrap()].name, skin);
+                    for (name, timelines) in slots.iter() {
+                        assert_eq!(&spine.slots[timelines.target.unwrap()].name, name);
+                    }
+                }
+            }
+        }
+
+        // Outdated targets are looked up by name
+        let mut spine =
+            Spine::parse(File::open("assets/spineboy/spineboy-pro.json").unwrap()).unwrap();
+        spine.bones.swap(1, 2);
+        for animation in spine.animations.values() {
+            for (name, timeline) in &animation.bones {
+                let index = timeline.target_in(name, &spine.bones, |bone| &bone.name);
+                assert_eq!(&spine.bones[index.unwrap()].name, name);
+            }
+        }
+
+        // Along with the ones never resolved
+        let timeline = spine::Timeline::from(spine::AnimationBone::default());
+        let name = &spine.bones[3].name;
+        assert_eq!(
+            timeline.target_in(name, &spine.bones, |bone| &bone.name),
+            Some(3)
+        );
+    }
+
+    #[test]
+    fn test_skin_composition() {
+        let spine =
+            Spine::parse(File::open("assets/mix-and-match/mix-and-match-pro.json").unwrap())
+                .unwrap();
+        let skin = |name: &str| spine.skins.iter().find(|skin| skin.name == name).unwrap();
+
+        let mut composed = spine::Skin::default();
+        composed.add_skin(skin("nose/short"));
+        composed.add_skin(skin("accessories/bag"));
+        composed.add_skin(skin("accessories/backpack"));
+        composed.add_skin(skin("nose/long"));
+        composed.add_skin(skin("accessories/bag"));
+
+        // Bones aren't duplicated
+        assert_eq!(composed.bones.len(), 9);
+
+        // Later skins replace the attachments
+        match composed.attachment("nose", "nose") {
+            Some(spine::SkinAttachment::Mesh { width, .. }) => assert_eq!(*width, Some(34)),
+            _ => panic!("nose mesh not found"),
+        }
+        assert!(composed.attachment("mouth", "mouth-close").is_some());
+    }
+
+    #[test]
+    fn test_linked_meshes() {
+        let binary =
+            Spine::parse_binary(File::open("assets/goblins/goblins-pro.skel").unwrap()).unwrap();
+        let json = Spine::parse(File::open("assets/goblins/goblins-pro.json").unwrap()).unwrap();
+
+        for spine in &[binary, json] {
+            let skin = |name: &str| spine.skins.iter().find(|skin| skin.name == name).unwrap();
+            let parent = skin("goblin").attachment("left-foot", "left-foot");
+            let linked = skin("goblingirl").attachment("left-foot", "left-foot");
+
+            // Geometry is shared but the region isn't
+            match (parent, linked.and_then(spine::SkinAttachment::mesh)) {
+                (
+                    Some(spine::SkinAttachment::Mesh {
+                        uvs: parent_uvs,
+                        path: parent_path,
+                        ..
+                    }),
+                    Some(spine::SkinAttachment::Mesh { uvs, path, .. }),
+                ) => {
+                    assert_eq!(parent_uvs, uvs);
+                    assert_ne!(parent_path, path);
+                }
+                _ => panic!("linked mesh not resolved"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_sequence() {
+        let attachment: spine::SkinAttachment = serde_json::from_str(
+            r#"{ "path": "fire", "width": 32, "height": 32, "sequence": { "count": 12, "digits": 2 } }"#,
+        )
+        .unwrap();
+        let sequence = attachment.sequence().unwrap();
+        assert_eq!(sequence.path("fire", 0), "fire01");
+        assert_eq!(sequence.path("fire", 11), "fire12");
+    }
+
+    #[test]
+    fn test_migrate_4_0() {
+        let spine = Spine::parse(
+            r#"{
+                "skeleton": { "spine": "4.0.64", "width": 10, "height": 10 },
+                "bones": [{ "name": "root" }],
+                "slots": [{ "name": "body", "bone": "root", "attachment": "body" }],
+                "skins": [{ "name": "default", "attachments": { "body": { "body": { "width": 10, "height": 10 } } } }],
+                "animations": {
+                    "grow": {
+                        "bones": {
+                            "root": {
+                                "scale": [
+                                    { "curve": [0.25, 1, 0.75, 1.5, 0.25, 1, 0.75, 3] },
+                                    { "time": 1, "x": 2, "y": 4 }
+                                ]
+                            }
+                        },
+                        "slots": {
+                            "body": {
+                                "rgba": [{ "color": "ff000080", "curve": "stepped" }, { "time": 1, "color": "00ff00ff" }],
+                                "rgba2": [{ "light": "ff0000ff", "dark": "00ff00" }]
+                            }
+                        }
+                    }
+                }
+            }"#
+            .as_bytes(),
+        )
+        .unwrap();
+
+        let animation = &spine.animations["grow"];
+
+        // The curve of the channel that changes the most is kept
+        let scale = &animation.bones["root"].scale;
+        assert_eq!((scale[1].x, scale[1].y), (2.0, 4.0));
+        match scale[0].curve {
+            spine::Interpolation::BezierCurve([cx1, cy1, cx2, cy2]) => {
+                assert_eq!((cx1, cy1, cx2), (0.25, 0.0, 0.75));
+                assert!((cy2 - 2.0 / 3.0).abs() < 1e-6);
+            }
+            _ => panic!("scale curve not migrated"),
+        }
+
+        let slot = &animation.slots["body"];
+        assert_eq!(slot.color[0].color, "ff000080");
+        assert!(matches!(slot.color[0].curve, spine::Interpolation::Stepped));
+        assert_eq!(slot.two_color[0].light, "ff0000ff");
+        assert_eq!(slot.two_color[0].dark, "00ff00");
+    }
+
+    #[test]
+    fn test_migrate_4_1() {
+        let spine = Spine::parse(
+            r#"{
+                "skeleton": { "spine": "4.1.24", "width": 10, "height": 10 },
+                "bones": [{ "name": "root" }],
+                "slots": [{ "name": "body", "bone": "root", "color": "ffffff80", "attachment": "body" }],
+                "skins": [{ "name": "default", "attachments": { "body": { "body": { "width": 10, "height": 10 } } } }],
+                "animations": {
+                    "walk": {
+                        "bones": {
+                            "root": {
+                                "translate": [
+                                    { "x": 0, "y": 0, "curve": [0.5, 0, 0.5, 10, 0.25, 0, 0.75, 0] },
+                                    { "time": 2, "x": 10 }
+                                ],
+                                "shearx": [{ "time": 1, "value": 45, "curve": "stepped" }, { "time": 2, "value": 90 }]
+                            }
+                        },
+                        "slots": {
+                            "body": {
+                                "rgb2": [{ "light": "ff0000", "dark": "0000ff" }, { "time": 1, "light": "00ff00", "dark": "000000" }]
+                            }
+                        }
+                    }
+                }
+            }"#
+            .as_bytes(),
+        )
+        .unwrap();
+
+        let animation = &spine.animations["walk"];
+        let bone = &animation.bones["root"];
+
+        // Curve times are relative to the keyframes
+        match bone.translate[0].curve {
+            spine::Interpolation::BezierCurve(curve) => assert_eq!(curve, [0.25, 0.0, 0.25, 1.0]),
+            _ => panic!("translate curve not migrated"),
+        }
+
+        // Stepped keys hold their value until the next key
+        let shear = |time: f32| {
+            let (a, b, t) = sample(&bone.shear, time).unwrap();
+            lerp(a.x, b.x, t)
+        };
+        assert_eq!(shear(1.5), 45.0);
+        assert_eq!(shear(2.0), 90.0);
+
+        // The light alpha isn't keyed, it's the setup alpha
+        let two_color = &animation.slots["body"].two_color;
+        let two_color = two_color
+            .iter()
+            .map(|k| (k.light.as_str(), k.dark.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            two_color,
+            vec![("ff000080", "0000ff"), ("00ff0080", "000000")]
+        );
+    }
+
+    #[test]
+    fn test_migrate_4_2() {
+        let spine = Spine::parse(
+            r#"{
+                "skeleton": { "spine": "4.2.11", "width": 10, "height": 10, "referenceScale": 100 },
+                "bones": [{ "name": "root" }, { "name": "arm", "parent": "root", "inherit": "noScale", "icon": "ik" }],
+                "slots": [{ "name": "arm", "bone": "arm", "color": "ffffff80", "attachment": "arm" }],
+                "transform": [{ "name": "follow", "bones": ["arm"], "target": "root", "mixRotate": 0.5, "mixX": 0.25, "mixY": 0.25 }],
+                "physics": [{ "name": "sway", "bone": "arm" }],
+                "skins": [{ "name": "default", "attachments": { "arm": { "arm": { "width": 10, "height": 10 } } } }],
+                "animations": {
+                    "walk": {
+                        "bones": {
+                            "arm": {
+                                "rotate": [{ "curve": [0.25, 0, 0.75, 90] }, { "time": 1, "value": 90 }],
+                                "translatex": [{ "value": 10 }, { "time": 1, "value": 20 }],
+                                "translatey": [{ "time": 0.5, "value": 5 }],
+                                "inherit": [{ "time": 0.5, "inherit": "normal" }]
+                            }
+                        },
+                        "slots": { "arm": { "rgb": [{ "color": "ff0000" }], "alpha": [{ "time": 1, "value": 0 }] } },
+                        "attachments": { "default": { "arm": { "arm": { "deform": [{ "vertices": [1, 2] }, { "time": 1 }] } } } },
+                        "physics": { "sway": { "wind": [{ "value": 1 }] } }
+                    }
+                }
+            }"#
+            .as_bytes(),
+        )
+        .unwrap();
+
+        assert!(matches!(
+            spine.bones[1].transform,
+            spine::InheritTransform::NoScale
+        ));
+        assert_eq!(spine.transform[0].rotate_mix, 0.5);
+        assert_eq!(spine.transform[0].translate_mix, 0.25);
+
+        let animation = &spine.animations["walk"];
+        let bone = &animation.bones["arm"];
+        assert_eq!(bone.rotate[1].angle, 90.0);
+        match bone.rotate[0].curve {
+            spine::Interpolation::BezierCurve(curve) => assert_eq!(curve, [0.25, 0.0, 0.75, 1.0]),
+            _ => panic!("rotate curve not migrated"),
+        }
+
+        // Axes keyed separately are merged, the y axis keeps its setup value until its first key
+        let translate = |time: f32| {
+            let (a, b, t) = sample(&bone.translate, time).unwrap();
+            (lerp(a.x, b.x, t), lerp(a.y, b.y, t))
+        };
+        assert_eq!(translate(0.0), (10.0, 0.0));
+        assert!((translate(0.25).0 - 12.5).abs() < 1e-3);
+        assert_eq!(translate(0.25).1, 0.0);
+        assert_eq!(translate(0.5), (15.0, 5.0));
+        assert_eq!(translate(0.75), (17.5, 5.0));
+        assert_eq!(translate(1.0), (20.0, 5.0));
+        assert_eq!(bone.inherit.len(), 1);
+
+        // Alpha keeps the setup value until its first key
+        let color = &animation.slots["arm"].color;
+        let (a, b, _) = sample(color, 0.5).unwrap();
+        assert_eq!(
+            (a.color.as_str(), b.color.as_str()),
+            ("ff000080", "ff000080")
+        );
+        let (a, _, _) = sample(color, 1.0).unwrap();
+        assert_eq!(a.color, "ff000000");
+
+        assert_eq!(animation.deform["default"]["arm"]["arm"].len(), 2);
+        assert_eq!(animation.duration(), 1.0);
+
+        let err = Spine::parse(r#"{ "skeleton": { "spine": "3.7.94" } }"#.as_bytes()).unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("unsupported spine version \"3.7.94\""),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_attachment_type() {
+        let skin: spine::Skin = serde_json::from_str(
+            r#"{
+                "name": "default",
+                "attachments": {
+                    "hand": {
+                        "socket": { "type": "point", "x": 2, "rotation": 90 },
+                        "hand": { "name": "hand-open", "width": 32, "height": 32 }
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+        match skin.attachment("hand", "socket") {
+            Some(spine::SkinAttachment::Point { x, rotation, .. }) => {
+                assert_eq!((*x, *rotation), (2.0, 90.0))
+            }
+            _ => panic!("point attachment not found"),
+        }
+        match skin.attachment("hand", "hand") {
+            Some(spine::SkinAttachment::Region { path, .. }) => {
+                assert_eq!(path.as_deref(), Some("hand-open"))
+            }
+            _ => panic!("region attachment not found"),
+        }
+
+        // Errors tell which attachment failed
+        let err = serde_json::from_str::<spine::Skin>(
+            r#"{ "name": "default", "attachments": { "hand": { "hitbox": { "type": "boundingbox" } } } }"#,
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(
+            err.starts_with(r#"skin "default", slot "hand", attachment "hitbox": missing field"#),
+            "{}",
+            err
+        );
+    }
 }
@@ -1,13 +1,19 @@
-use std::{collections::HashMap};
 use std::default::Default;
 use std::io::{BufReader, Read};
+use std::{
+    collections::HashMap,
+    convert::TryFrom,
+    ops::{Deref, DerefMut},
+};
 
-use anyhow::Error;
-use serde::{Deserialize, Serialize};
-use serde_json::from_reader;
+use anyhow::{bail, Error};
+use bevy::reflect::{Reflect, TypeUuid};
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{from_reader, Value};
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, TypeUuid)]
 #[serde(deny_unknown_fields, rename_all = "camelCase")]
+#[uuid = "5542cb03-3e54-40c7-a9ad-4a338104b71f"]
 pub struct Spine {
     pub slots: Vec<Slot>,
     pub skeleton: Skeleton,
@@ -25,11 +31,157 @@ pub struct Spine {
 }
 
 impl Spine {
+    /// Parses the spine json format, files exported by the editor versions `3.8.x`, `4.0.x`, `4.1.x`
+    /// and `4.2.x` are supported, see [`migrate`](super::migrate) module for more info
     pub fn parse<R: Read>(reader: R) -> Result<Spine, Error> {
         let r = BufReader::new(reader);
-        let spine: Spine = from_reader(r)?;
+        let mut value: Value = from_reader(r)?;
+        super::migrate::migrate(&mut value)?;
+        let mut spine: Spine = serde_json::from_value(value)?;
+        spine.link_meshes()?;
+        spine.resolve_timelines();
+        Ok(spine)
+    }
+
+    /// Parses the spine binary format (`.skel`), see [`binary`](super::binary) module for more info
+    pub fn parse_binary<R: Read>(reader: R) -> Result<Spine, Error> {
+        let mut r = BufReader::new(reader);
+        let mut buf = Vec::new();
+        r.read_to_end(&mut buf)?;
+        let mut spine = super::binary::parse(&buf)?;
+        spine.link_meshes()?;
+        spine.resolve_timelines();
         Ok(spine)
     }
+
+    /// Resolves the mesh of every [`SkinAttachment::LinkedMesh`], parent meshes may be found in any skin
+    /// and may also be linked meshes
+    pub fn link_meshes(&mut self) -> Result<(), Error> {
+        loop {
+            let mut resolved = vec![];
+            let mut unresolved = None;
+            for (skin_index, skin) in self.skins.iter().enumerate() {
+                for (slot, attachments) in &skin.attachments {
+                    for (name, attachment) in attachments {
+                        let (parent_skin, parent, path, color, width, height, sequence) =
+                            match attachment {
+                                SkinAttachment::LinkedMesh {
+                                    skin,
+                                    parent,
+                                    path,
+                                    color,
+                                    width,
+                                    height,
+                                    sequence,
+                                    mesh: None,
+                                    ..
+                                } => (skin, parent, path, color, width, height, sequence),
+                                _ => continue,
+                            };
+
+                        let parent_skin = parent_skin.as_deref().unwrap_or("default");
+                        let parent_mesh = self
+                            .skins
+                            .iter()
+                            .find(|skin| skin.name == parent_skin)
+                            .and_then(|skin| skin.attachment(slot, parent))
+                            .and_then(SkinAttachment::mesh);
+
+                        if let Some(SkinAttachment::Mesh {
+                            uvs,
+                            triangles,
+                            vertices,
+                            hull,
+                            edges,
+                            ..
+                        }) = parent_mesh
+                        {
+                            let mesh = SkinAttachment::Mesh {
+                                path: path.clone(),
+                                uvs: uvs.clone(),
+                                triangles: triangles.clone(),
+                                vertices: vertices.clone(),
+                                hull: *hull,
+                                edges: edges.clone(),
+                                color: color.clone(),
+                                width: *width,
+                                height: *height,
+                                sequence: sequence.clone(),
+                            };
+                            resolved.push((skin_index, slot.clone(), name.clone(), mesh));
+                        } else {
+                            unresolved = Some((skin, slot, name, parent_skin, parent));
+                        }
+                    }
+                }
+            }
+
+            if resolved.is_empty() {
+                if let Some((skin, slot, name, parent_skin, parent)) = unresolved {
+                    bail!(
+                        "skin \"{}\", slot \"{}\", attachment \"{}\": parent mesh \"{}\" not found in skin \"{}\"",
+                        skin.name,
+                        slot,
+                        name,
+                        parent,
+                        parent_skin
+                    );
+                }
+                return Ok(());
+            }
+
+            for (skin_index, slot, name, resolved_mesh) in resolved {
+                let attachment = self.skins[skin_index]
+                    .attachments
+                    .get_mut(&slot)
+                    .and_then(|attachments| attachments.get_mut(&name));
+                if let Some(SkinAttachment::LinkedMesh { mesh, .. }) = attachment {
+                    *mesh = Some(Box::new(resolved_mesh));
+                }
+            }
+        }
+    }
+
+    /// Resolves the [`Timeline::target`] of every animation timeline, so they aren't looked up by name
+    /// when sampled; Timelines added later (or with outdated targets) still work, but are looked up by name
+    pub fn resolve_timelines(&mut self) {
+        let Spine {
+            animations,
+            bones,
+            ik,
+            transform,
+            path,
+            skins,
+            slots,
+            ..
+        } = self;
+
+        for animation in animations.values_mut() {
+            for (name, timeline) in &mut animation.bones {
+                timeline.target = timeline.target_in(name, bones, |bone| &bone.name);
+            }
+            for (name, timeline) in &mut animation.ik {
+                timeline.target = timeline.target_in(name, ik, |ik| &ik.name);
+            }
+            for (name, timeline) in &mut animation.transform {
+                timeline.target = timeline.target_in(name, transform, |t| &t.name);
+            }
+            for (name, timeline) in &mut animation.path {
+                timeline.target = timeline.target_in(name, path, |path| &path.name);
+            }
+            for (name, timeline) in &mut animation.slots {
+                timeline.target = timeline.target_in(name, slots, |slot| &slot.name);
+            }
+            for keyframe in &mut animation.draw_order {
+                for offset in &mut keyframe.offsets {
+                    offset.target =
+                        find_target(offset.target, &offset.slot, slots, |slot| &slot.name);
+                }
+            }
+            resolve_skin_timelines(&mut animation.deform, skins, slots);
+            resolve_skin_timelines(&mut animation.attachments, skins, slots);
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -45,7 +197,9 @@ pub struct Skeleton {
     pub height: f32,
     #[serde(default = "default_fps")]
     pub fps: u32,
+    #[serde(default)]
     pub images: String,
+    #[serde(default)]
     pub audio: String,
 }
 
@@ -173,7 +327,8 @@ pub struct PathConstraints {
     pub translate_mix: f32,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Reflect, Clone, Copy, Debug, PartialEq)]
+#[reflect_value(PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub enum PositionMode {
     Fixed,
@@ -186,7 +341,8 @@ impl Default for PositionMode {
     }
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Reflect, Clone, Copy, Debug, PartialEq)]
+#[reflect_value(PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub enum SpacingMode {
     Length,
@@ -200,7 +356,8 @@ impl Default for SpacingMode {
     }
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Reflect, Clone, Copy, Debug, PartialEq)]
+#[reflect_value(PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub enum RotateMode {
     Tangent,
@@ -217,16 +374,141 @@ impl Default for RotateMode {
 #[derive(Default, Serialize, Deserialize, Clone, Debug)]
 #[serde(default, deny_unknown_fields, rename_all = "camelCase")]
 pub struct Animation {
-    pub slots: HashMap<String, AnimationSlot>,
-    pub bones: HashMap<String, AnimationBone>,
-    pub ik: HashMap<String, Vec<AnimationIk>>,
-    pub transform: HashMap<String, Vec<AnimationTransform>>,
-    pub path: HashMap<String, AnimationPath>,
-    pub deform: HashMap<String, HashMap<String, HashMap<String, Vec<AnimationDeform>>>>,
+    pub slots: HashMap<String, Timeline<AnimationSlot>>,
+    pub bones: HashMap<String, Timeline<AnimationBone>>,
+    pub ik: HashMap<String, Timeline<Vec<AnimationIk>>>,
+    pub transform: HashMap<String, Timeline<Vec<AnimationTransform>>>,
+    pub path: HashMap<String, Timeline<AnimationPath>>,
+    pub deform: SkinTimelines<Vec<AnimationDeform>>,
+    /// Attachments timelines indexed by skin, slot and attachment names
+    pub attachments: SkinTimelines<AnimationAttachment>,
     pub draw_order: Vec<AnimationDrawOrder>,
     pub events: Vec<AnimationEvent>,
 }
 
+/// Timeline keys along side the index of the skeleton part they animate, see [`Spine::resolve_timelines`]
+#[derive(Default, Serialize, Deserialize, Clone, Debug)]
+#[serde(transparent)]
+pub struct Timeline<T> {
+    pub keys: T,
+    /// Index of the timeline target, resolved after parsing
+    #[serde(skip)]
+    pub target: Option<usize>,
+}
+
+impl<T> Timeline<T> {
+    /// Index of the timeline target named `name` in `targets`, the resolved [`target`](Self::target)
+    /// is only used while it still points to a target with the same name
+    pub fn target_in<E>(
+        &self,
+        name: &str,
+        targets: &[E],
+        target_name: impl Fn(&E) -> &str,
+    ) -> Option<usize> {
+        find_target(self.target, name, targets, target_name)
+    }
+}
+
+/// Index of the target named `name` in `targets`, the `resolved` index is only used while it still
+/// points to a target with the same name
+pub fn find_target<E>(
+    resolved: Option<usize>,
+    name: &str,
+    targets: &[E],
+    target_name: impl Fn(&E) -> &str,
+) -> Option<usize> {
+    let matches = |target: &E| target_name(target) == name;
+    resolved
+        .filter(|index| targets.get(*index).map_or(false, &matches))
+        .or_else(|| targets.iter().position(matches))
+}
+
+impl<T> From<T> for Timeline<T> {
+    fn from(keys: T) -> Self {
+        Self { keys, target: None }
+    }
+}
+
+impl<T> Deref for Timeline<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.keys
+    }
+}
+
+impl<T> DerefMut for Timeline<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.keys
+    }
+}
+
+/// Attachments timelines indexed by skin, slot and attachment names, along side the skin and slot indices
+pub type SkinTimelines<T> =
+    HashMap<String, Timeline<HashMap<String, Timeline<HashMap<String, T>>>>>;
+
+fn resolve_skin_timelines<T>(timelines: &mut SkinTimelines<T>, skins: &[Skin], slots: &[Slot]) {
+    for (skin_name, skin_timelines) in timelines {
+        skin_timelines.target = skin_timelines.target_in(skin_name, skins, |skin| &skin.name);
+        for (slot_name, slot_timelines) in skin_timelines.iter_mut() {
+            slot_timelines.target = slot_timelines.target_in(slot_name, slots, |slot| &slot.name);
+        }
+    }
+}
+
+impl Animation {
+    /// Animation duration in seconds, given by the time of it's last keyframe
+    pub fn duration(&self) -> f32 {
+        fn last<T>(keyframes: &[T], time: impl Fn(&T) -> f32) -> f32 {
+            keyframes.last().map_or(0.0, time)
+        }
+
+        let mut duration: f32 = 0.0;
+        for slot in self.slots.values() {
+            duration = duration
+                .max(last(&slot.attachment, |k| k.time))
+                .max(last(&slot.color, |k| k.time))
+                .max(last(&slot.two_color, |k| k.time));
+        }
+        for bone in self.bones.values() {
+            duration = duration
+                .max(last(&bone.scale, |k| k.time))
+                .max(last(&bone.rotate, |k| k.time))
+                .max(last(&bone.translate, |k| k.time))
+                .max(last(&bone.shear, |k| k.time))
+                .max(last(&bone.inherit, |k| k.time));
+        }
+        for ik in self.ik.values() {
+            duration = duration.max(last(ik, |k| k.time));
+        }
+        for transform in self.transform.values() {
+            duration = duration.max(last(transform, |k| k.time));
+        }
+        for path in self.path.values() {
+            duration = duration
+                .max(last(&path.position, |k| k.time))
+                .max(last(&path.spacing, |k| k.time))
+                .max(last(&path.mix, |k| k.time));
+        }
+        for deform in self.deform.values().flat_map(|slots| slots.values()) {
+            for keyframes in deform.values() {
+                duration = duration.max(last(keyframes, |k| k.time));
+            }
+        }
+        for attachment in self
+            .attachments
+            .values()
+            .flat_map(|slots| slots.values())
+            .flat_map(|attachments| attachments.values())
+        {
+            duration = duration.max(last(&attachment.sequence, |k| k.time));
+        }
+        duration
+            .max(last(&self.draw_order, |k| k.time))
+            .max(last(&self.events, |k| k.time))
+    }
+}
+
 #[derive(Default, Serialize, Deserialize, Clone, Debug)]
 #[serde(default, deny_unknown_fields,  rename_all = "camelCase")]
 pub struct AnimationSlot {
@@ -267,6 +549,8 @@ pub struct AnimationBone {
     pub rotate: Vec<RotateKeyframe>,
     pub translate: Vec<TranslateKeyframe>,
     pub shear: Vec<ShearKeyframe>,
+    /// Transform inheritance keyframes, since 4.2
+    pub inherit: Vec<InheritKeyframe>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -319,6 +603,13 @@ pub struct ShearKeyframe {
     pub curve: Interpolation,
 }
 
+#[derive(Default, Serialize, Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields, rename_all = "camelCase")]
+pub struct InheritKeyframe {
+    pub time: f32,
+    pub inherit: InheritTransform,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
 pub enum Interpolation {
@@ -349,8 +640,7 @@ mod keyframe_interpolation {
         S: Serializer,
     {
         match interpolation {
-            // Should be skipped
-            Interpolation::Linear => unreachable!(),
+            Interpolation::Linear => serializer.serialize_map(Some(0))?.end(),
             Interpolation::Stepped => {
                 let mut map = serializer.serialize_map(Some(1))?;
                 map.serialize_entry("curve", "stepped")?;
@@ -440,12 +730,14 @@ pub struct AnimationIk {
     pub mix: f32,
     /// A value for two bone IK, the distance from the maximum reach of the bones that rotation will slow. Assume 0 if omitted.
     pub softness: f32,
-    /// The IK constraint bend direction for the keyframe. Assume false if omitted.
+    /// The IK constraint bend direction for the keyframe. Assume true if omitted.
     pub bend_positive: bool,
     /// If true, and only a single bone is being constrained, if the target is too close, the bone is scaled to reach it. Assume false if omitted.
     pub compress: bool,
     /// If true, and if the target is out of range, the parent bone is scaled to reach it. If more than one bone is being constrained and the parent bone has local nonuniform scale, stretch is not applied. Assume false if omitted.
     pub stretch: bool,
+    #[serde(flatten, with = "keyframe_interpolation")]
+    pub curve: Interpolation,
 }
 
 impl Default for AnimationIk {
@@ -454,9 +746,10 @@ impl Default for AnimationIk {
             time: 0.0,
             mix: 1.0,
             softness: 0.0,
-            bend_positive: false,
+            bend_positive: true,
             compress: false,
             stretch: false,
+            curve: Default::default(),
         }
     }
 }
@@ -507,7 +800,7 @@ impl Default for PathPositionKeyframe {
     fn default() -> Self {
         Self {
             time: 0.0,
-            position: 1.0,
+            position: 0.0,
             curve: Default::default(),
         }
     }
@@ -526,7 +819,7 @@ impl Default for PathSpacingKeyframe {
     fn default() -> Self {
         Self {
             time: 0.0,
-            spacing: 1.0,
+            spacing: 0.0,
             curve: Default::default(),
         }
     }
@@ -563,6 +856,41 @@ pub struct AnimationDeform {
     pub curve: Interpolation,
 }
 
+#[derive(Default, Serialize, Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields, rename_all = "camelCase")]
+pub struct AnimationAttachment {
+    pub sequence: Vec<SequenceKeyframe>,
+}
+
+#[derive(Default, Serialize, Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields, rename_all = "camelCase")]
+pub struct SequenceKeyframe {
+    pub time: f32,
+    pub mode: SequenceMode,
+    /// Frame shown at the keyframe time
+    pub index: usize,
+    /// Seconds between frames
+    pub delay: f32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum SequenceMode {
+    Hold,
+    Once,
+    Loop,
+    Pingpong,
+    OnceReverse,
+    LoopReverse,
+    PingpongReverse,
+}
+
+impl Default for SequenceMode {
+    fn default() -> Self {
+        SequenceMode::Hold
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(deny_unknown_fields, rename_all = "camelCase")]
 pub struct AnimationDrawOrder {
@@ -576,38 +904,27 @@ pub struct AnimationDrawOrder {
 pub struct DrawOrderOffset {
     pub slot: String,
     pub offset: i32,
+    /// Index of the slot, resolved after parsing
+    #[serde(skip)]
+    pub target: Option<usize>,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+/// Event keyed in an animation, values not given by the key are taken from the [`Event`] data
+#[derive(Default, Serialize, Deserialize, Clone, Debug)]
 #[serde(default, deny_unknown_fields, rename_all = "camelCase")]
 pub struct AnimationEvent {
     pub name: Option<String>,
     pub time: f32,
-    pub int: isize,
-    pub float: f32,
+    pub int: Option<isize>,
+    pub float: Option<f32>,
     pub string: Option<String>,
     pub audio: Option<String>,
-    pub volume: f32,
-    pub balance: f32,
-}
-
-impl Default for AnimationEvent {
-    fn default() -> Self {
-        Self {
-            name: None,
-            time: 0.0,
-            int: 0,
-            float: 0.0,
-            string: None,
-            audio: None,
-            volume: 1.0,
-            balance: 0.0,
-        }
-    }
+    pub volume: Option<f32>,
+    pub balance: Option<f32>,
 }
 
 #[derive(Default, Serialize, Deserialize, Clone, Debug)]
-#[serde(default, deny_unknown_fields, rename_all = "camelCase")]
+#[serde(try_from = "SkinData", rename_all = "camelCase")]
 pub struct Skin {
     pub name: String,
     pub bones: Vec<String>,
@@ -617,31 +934,146 @@ pub struct Skin {
     pub attachments: HashMap<String, HashMap<String, SkinAttachment>>,
 }
 
+impl Skin {
+    /// Adds the bones, constraints and attachments of `skin` to this skin,
+    /// attachments already in this skin are replaced by the ones with same slot and name
+    pub fn add_skin(&mut self, skin: &Skin) {
+        fn extend(names: &mut Vec<String>, other: &[String]) {
+            for name in other {
+                if !names.contains(name) {
+                    names.push(name.clone());
+                }
+            }
+        }
+
+        extend(&mut self.bones, &skin.bones);
+        extend(&mut self.ik, &skin.ik);
+        extend(&mut self.transform, &skin.transform);
+        extend(&mut self.path, &skin.path);
+
+        for (slot, attachments) in &skin.attachments {
+            let slot_attachments = self.attachments.entry(slot.clone()).or_default();
+            for (name, attachment) in attachments {
+                slot_attachments.insert(name.clone(), attachment.clone());
+            }
+        }
+    }
+
+    /// Finds the `attachment` of the `slot`
+    pub fn attachment(&self, slot: &str, attachment: &str) -> Option<&SkinAttachment> {
+        self.attachments
+            .get(slot)
+            .and_then(|attachments| attachments.get(attachment))
+    }
+}
+
+/// [`Skin`] as found in the json, attachments are parsed once their slot and name are known
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "camelCase")]
+struct SkinData {
+    name: String,
+    bones: Vec<String>,
+    ik: Vec<String>,
+    transform: Vec<String>,
+    path: Vec<String>,
+    attachments: HashMap<String, HashMap<String, Value>>,
+}
+
+impl TryFrom<SkinData> for Skin {
+    type Error = String;
+
+    fn try_from(data: SkinData) -> Result<Self, Self::Error> {
+        let SkinData {
+            name,
+            bones,
+            ik,
+            transform,
+            path,
+            attachments: data,
+        } = data;
+
+        let mut attachments = HashMap::with_capacity(data.len());
+        for (slot, slot_attachments) in data {
+            let mut parsed = HashMap::with_capacity(slot_attachments.len());
+            for (attachment_name, mut value) in slot_attachments {
+                // Name used by the attachment, defaults to the skin placeholder name
+                if let Value::Object(map) = &mut value {
+                    if !map.contains_key("path") {
+                        if let Some(Value::String(path)) = map.get("name") {
+                            if *path != attachment_name {
+                                let path = path.clone();
+                                map.insert("path".to_string(), Value::String(path));
+                            }
+                        }
+                    }
+                }
+
+                let attachment =
+                    serde_json::from_value::<SkinAttachment>(value).map_err(|err| {
+                        format!(
+                            "skin \"{}\", slot \"{}\", attachment \"{}\": {}",
+                            name, slot, attachment_name, err
+                        )
+                    })?;
+                parsed.insert(attachment_name, attachment);
+            }
+            attachments.insert(slot, parsed);
+        }
+
+        Ok(Skin {
+            name,
+            bones,
+            ik,
+            transform,
+            path,
+            attachments,
+        })
+    }
+}
+
+/// Attachment of a [`Skin`], the json `"type"` tells the variant and defaults to `"region"`
 #[derive(Serialize, Deserialize, Clone, Debug)]
-#[serde(untagged, rename_all = "camelCase")]
+#[serde(remote = "Self", tag = "type", rename_all = "lowercase")]
 pub enum SkinAttachment {
-    // "tag" is omitted 
     #[serde(rename_all = "camelCase")]
-    Region {
-        path: Option<String>,
+    Path {
+        #[serde(default)]
+        closed: bool,
+        #[serde(default = "yes")]
+        constant_speed: bool,
+        lengths: Vec<f32>,
+        vertex_count: usize,
+        vertices: Vec<f32>,
+        #[serde(default = "path_color")]
+        color: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    Clipping {
+        // Last clipped slot, every slot drawn after the clipping slot is clipped when omitted
+        end: Option<String>,
+        vertex_count: usize,
+        vertices: Vec<f32>,
+        #[serde(default = "clipping_color")]
+        color: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    BoundingBox {
+        vertex_count: usize,
+        vertices: Vec<f32>,
+        #[serde(default = "bounding_box_color")]
+        color: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    Point {
         #[serde(default)]
         x: f32,
         #[serde(default)]
         y: f32,
-        #[serde(default = "one_f32")]
-        scale_x: f32,
-        #[serde(default = "one_f32")]
-        scale_y: f32,
         #[serde(default)]
         rotation: f32,
-        #[serde(default)]
-        width: u32,
-        #[serde(default)]
-        height: u32,
-        #[serde(default = "white_color")]
+        #[serde(default = "point_color")]
         color: String,
     },
-    // "tag": "mesh"
     #[serde(rename_all = "camelCase")]
     Mesh {
         path: Option<String>,
@@ -654,8 +1086,115 @@ pub enum SkinAttachment {
         color: String,
         width: Option<u32>,
         height: Option<u32>,
+        sequence: Option<Sequence>,
     },
-    // TODO: implement other variants
+    // Uses the geometry of its `parent` mesh
+    #[serde(rename_all = "camelCase")]
+    LinkedMesh {
+        path: Option<String>,
+        // Skin of the parent mesh, the default skin when omitted
+        skin: Option<String>,
+        parent: String,
+        // Deformed by the parent mesh deform timelines, named "timelines" since 4.0
+        #[serde(default = "yes", alias = "timelines")]
+        deform: bool,
+        #[serde(default = "white_color")]
+        color: String,
+        width: Option<u32>,
+        height: Option<u32>,
+        sequence: Option<Sequence>,
+        // Resolved once every skin is loaded
+        #[serde(skip)]
+        mesh: Option<Box<SkinAttachment>>,
+    },
+    #[serde(rename_all = "camelCase")]
+    Region {
+        path: Option<String>,
+        #[serde(default)]
+        x: f32,
+        #[serde(default)]
+        y: f32,
+        #[serde(default = "one_f32")]
+        scale_x: f32,
+        #[serde(default = "one_f32")]
+        scale_y: f32,
+        #[serde(default)]
+        rotation: f32,
+        #[serde(default)]
+        width: u32,
+        #[serde(default)]
+        height: u32,
+        #[serde(default = "white_color")]
+        color: String,
+        sequence: Option<Sequence>,
+    },
+}
+
+impl Serialize for SkinAttachment {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SkinAttachment::serialize(self, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for SkinAttachment {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut value = Value::deserialize(deserializer)?;
+        if let Value::Object(map) = &mut value {
+            map.entry("type")
+                .or_insert_with(|| Value::String("region".to_string()));
+        }
+        SkinAttachment::deserialize(value).map_err(D::Error::custom)
+    }
+}
+
+impl SkinAttachment {
+    /// Mesh attachment or the mesh resolved for a linked mesh, see [`Spine::link_meshes`]
+    pub fn mesh(&self) -> Option<&SkinAttachment> {
+        match self {
+            SkinAttachment::Mesh { .. } => Some(self),
+            SkinAttachment::LinkedMesh { mesh, .. } => mesh.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Frame by frame sequence of the region or mesh
+    pub fn sequence(&self) -> Option<&Sequence> {
+        match self {
+            SkinAttachment::Region { sequence, .. }
+            | SkinAttachment::Mesh { sequence, .. }
+            | SkinAttachment::LinkedMesh { sequence, .. } => sequence.as_ref(),
+            _ => None,
+        }
+    }
+}
+
+/// Regions shown frame by frame, played back by the sequence timelines
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+pub struct Sequence {
+    /// Number of frames
+    pub count: usize,
+    /// Number added to the attachment path of the first frame
+    #[serde(default = "one_usize")]
+    pub start: usize,
+    /// Frame number digits, padded with zeros
+    #[serde(default)]
+    pub digits: usize,
+    /// Frame shown in the setup pose
+    #[serde(default)]
+    pub setup: usize,
+}
+
+impl Sequence {
+    /// Region path of the frame `index`, e.g. `"fire"` becomes `"fire01"`
+    pub fn path(&self, path: &str, index: usize) -> String {
+        format!(
+            "{}{:0width$}",
+            path,
+            self.start + index,
+            width = self.digits
+        )
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -679,11 +1218,15 @@ pub struct IkConstraints {
     pub name: String,
     #[serde(default)]
     pub order: usize,
+    #[serde(default)]
+    pub skin: bool,
     pub bones: Vec<String>,
     pub target: String,
     #[serde(default = "one_f32")]
     pub mix: f32,
     #[serde(default)]
+    pub softness: f32,
+    #[serde(default = "yes")]
     pub bend_positive: bool,
     #[serde(default)]
     pub compress: bool,
@@ -697,25 +1240,51 @@ pub struct IkConstraints {
 #[serde(deny_unknown_fields, rename_all = "camelCase")]
 pub struct Event {
     #[serde(default)]
-    int: i64,
+    pub int: i64,
+    #[serde(default)]
+    pub float: f32,
+    #[serde(default)]
+    pub string: Option<String>,
     #[serde(default)]
-    float: f32,
+    pub audio: Option<String>,
+    #[serde(default = "one_f32")]
+    pub volume: f32,
     #[serde(default)]
-    string: Option<String>,
+    pub balance: f32,
 }
 
 fn one_f32() -> f32 {
     1.0
 }
 
+fn one_usize() -> usize {
+    1
+}
+
 fn yes() -> bool {
     true
 }
 
 fn bone_default_color() -> String {
-    "989898FF".to_string()
+    "9B9B9BFF".to_string()
 }
 
 fn white_color() -> String {
     "FFFFFFFF".to_owned()
 }
+
+fn path_color() -> String {
+    "FF7F00FF".to_owned()
+}
+
+fn bounding_box_color() -> String {
+    "60F000FF".to_owned()
+}
+
+fn point_color() -> String {
+    "F1F100FF".to_owned()
+}
+
+fn clipping_color() -> String {
+    "CE3A3AFF".to_owned()
+}
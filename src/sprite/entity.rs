@@ -12,7 +12,7 @@ use bevy::{
 
 use super::{
     render::SPRITE_PIPELINE_HANDLE,
-    sprite::{Sprite, SpriteInstance},
+    sprite::{Sprite, SpriteBlend, SpriteInstance},
 };
 use crate::transform::{LocalToWorld, LocalToWorld2D, Transform2D};
 
@@ -26,6 +26,7 @@ pub type SpriteBundle = SpriteBundleBase<Transform, LocalToWorld>;
 pub struct SpriteBundleBase<T: Send + Sync + 'static, M: Send + Sync + 'static> {
     pub sprite: Handle<Sprite>,
     pub sprite_instance: SpriteInstance,
+    pub blend: SpriteBlend,
     pub mesh: Handle<Mesh>,
     pub main_pass: MainPass,
     pub draw: Draw,
@@ -44,6 +45,7 @@ where
         Self {
             sprite: Default::default(),
             sprite_instance: Default::default(),
+            blend: Default::default(),
             mesh: Default::default(),
             render_pipelines: RenderPipelines::from_pipelines(vec![RenderPipeline::new(
                 SPRITE_PIPELINE_HANDLE.typed(),
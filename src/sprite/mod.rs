@@ -19,7 +19,7 @@ pub use render::*;
 pub use sprite::{Sprite, *};
 
 pub mod prelude {
-    pub use super::{entity::SpriteBundleBase, Sprite, SpriteInstance};
+    pub use super::{entity::SpriteBundleBase, Sprite, SpriteBlend, SpriteInstance};
 }
 
 #[derive(Debug, Hash, PartialEq, Eq, Clone, StageLabel)]
@@ -35,6 +35,7 @@ impl Plugin for SpritePlugin {
         // Sprite
         app.add_asset::<Sprite>()
             .register_type::<SpriteInstance>()
+            .register_type::<SpriteBlend>()
             .add_stage_after(
                 AssetStage::AssetEvents,
                 SpriteStage::Update,
@@ -48,7 +49,11 @@ impl Plugin for SpritePlugin {
                 SpriteStage::Update,
                 sprite::rebuild_modified_sprite_system.system(),
             )
-            .add_system_to_stage(SpriteStage::Update, sprite::update_sprite_system.system());
+            .add_system_to_stage(SpriteStage::Update, sprite::update_sprite_system.system())
+            .add_system_to_stage(
+                SpriteStage::Update,
+                sprite::update_sprite_pipeline_system.system(),
+            );
 
         let world = app.world_mut();
 
@@ -72,12 +77,23 @@ impl Plugin for SpritePlugin {
             .add_node_edge(node::SPRITE, base::node::MAIN_PASS)
             .unwrap();
 
+        // One pipeline for each blend mode, with and without premultiplied alpha
         let mut shaders = world.get_resource_mut::<Assets<Shader>>().unwrap();
-        let pipeline = build_sprite_pipeline(&mut shaders);
+        let mut sprite_pipelines = vec![];
+        for blend in SpriteBlend::ALL.iter().copied() {
+            for pma in [false, true].iter().copied() {
+                sprite_pipelines.push((
+                    sprite_pipeline_handle(blend, pma),
+                    build_sprite_pipeline(&mut shaders, blend, pma),
+                ));
+            }
+        }
 
         let mut pipelines = world
             .get_resource_mut::<Assets<PipelineDescriptor>>()
             .unwrap();
-        pipelines.set_untracked(SPRITE_PIPELINE_HANDLE, pipeline);
+        for (handle, pipeline) in sprite_pipelines {
+            pipelines.set_untracked(handle, pipeline);
+        }
     }
 }
@@ -1,5 +1,5 @@
 use bevy::{
-    asset::{Assets, HandleUntyped},
+    asset::{Assets, HandleId, HandleUntyped},
     reflect::TypeUuid,
     render::{
         pipeline::{
@@ -12,10 +12,93 @@ use bevy::{
     },
 };
 
+use super::sprite::SpriteBlend;
+
 pub const SPRITE_PIPELINE_HANDLE: HandleUntyped =
     HandleUntyped::weak_from_u64(PipelineDescriptor::TYPE_UUID, 0xf8c045f774bd9729);
 
-pub fn build_sprite_pipeline(shaders: &mut Assets<Shader>) -> PipelineDescriptor {
+/// Pipeline used by sprites with premultiplied alpha textures
+pub const SPRITE_PMA_PIPELINE_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(PipelineDescriptor::TYPE_UUID, 0x2d3e6a0c94b15f87);
+
+pub const SPRITE_ADDITIVE_PIPELINE_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(PipelineDescriptor::TYPE_UUID, 0x6b1f0e3d9a27c458);
+
+pub const SPRITE_PMA_ADDITIVE_PIPELINE_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(PipelineDescriptor::TYPE_UUID, 0xc3a85e1f2d904b76);
+
+pub const SPRITE_MULTIPLY_PIPELINE_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(PipelineDescriptor::TYPE_UUID, 0x1e9d47b06c3a85f2);
+
+pub const SPRITE_PMA_MULTIPLY_PIPELINE_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(PipelineDescriptor::TYPE_UUID, 0x8f26c0d4b15e93a7);
+
+pub const SPRITE_SCREEN_PIPELINE_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(PipelineDescriptor::TYPE_UUID, 0x5a7c3e92f0d18b64);
+
+pub const SPRITE_PMA_SCREEN_PIPELINE_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(PipelineDescriptor::TYPE_UUID, 0xd04b6a183e7f25c9);
+
+/// Pipeline used by sprites with the `blend` mode, with or without premultiplied alpha textures
+pub fn sprite_pipeline_handle(blend: SpriteBlend, pma: bool) -> HandleUntyped {
+    match (blend, pma) {
+        (SpriteBlend::Normal, false) => SPRITE_PIPELINE_HANDLE,
+        (SpriteBlend::Normal, true) => SPRITE_PMA_PIPELINE_HANDLE,
+        (SpriteBlend::Additive, false) => SPRITE_ADDITIVE_PIPELINE_HANDLE,
+        (SpriteBlend::Additive, true) => SPRITE_PMA_ADDITIVE_PIPELINE_HANDLE,
+        (SpriteBlend::Multiply, false) => SPRITE_MULTIPLY_PIPELINE_HANDLE,
+        (SpriteBlend::Multiply, true) => SPRITE_PMA_MULTIPLY_PIPELINE_HANDLE,
+        (SpriteBlend::Screen, false) => SPRITE_SCREEN_PIPELINE_HANDLE,
+        (SpriteBlend::Screen, true) => SPRITE_PMA_SCREEN_PIPELINE_HANDLE,
+    }
+}
+
+/// Is one of the sprite pipelines, see [`sprite_pipeline_handle`]
+pub fn is_sprite_pipeline(id: HandleId) -> bool {
+    SpriteBlend::ALL.iter().any(|blend| {
+        sprite_pipeline_handle(*blend, false).id == id
+            || sprite_pipeline_handle(*blend, true).id == id
+    })
+}
+
+/// Builds the pipeline of the `blend` mode, as done by the spine runtimes
+pub fn build_sprite_pipeline(
+    shaders: &mut Assets<Shader>,
+    blend: SpriteBlend,
+    pma: bool,
+) -> PipelineDescriptor {
+    let (src_factor, dst_factor) = match blend {
+        SpriteBlend::Normal => (
+            if pma {
+                BlendFactor::One
+            } else {
+                BlendFactor::SrcAlpha
+            },
+            BlendFactor::OneMinusSrcAlpha,
+        ),
+        SpriteBlend::Additive => (
+            if pma {
+                BlendFactor::One
+            } else {
+                BlendFactor::SrcAlpha
+            },
+            BlendFactor::One,
+        ),
+        SpriteBlend::Multiply => (BlendFactor::DstColor, BlendFactor::OneMinusSrcAlpha),
+        SpriteBlend::Screen => (BlendFactor::One, BlendFactor::OneMinusSrcColor),
+    };
+
+    sprite_pipeline(
+        shaders,
+        BlendState {
+            src_factor,
+            dst_factor,
+            operation: BlendOperation::Add,
+        },
+    )
+}
+
+fn sprite_pipeline(shaders: &mut Assets<Shader>, color_blend: BlendState) -> PipelineDescriptor {
     PipelineDescriptor {
         depth_stencil: Some(DepthStencilState {
             format: TextureFormat::Depth32Float,
@@ -36,11 +119,7 @@ pub fn build_sprite_pipeline(shaders: &mut Assets<Shader>) -> PipelineDescriptor
         }),
         color_target_states: vec![ColorTargetState {
             format: TextureFormat::default(),
-            color_blend: BlendState {
-                src_factor: BlendFactor::SrcAlpha,
-                dst_factor: BlendFactor::OneMinusSrcAlpha,
-                operation: BlendOperation::Add,
-            },
+            color_blend,
             alpha_blend: BlendState {
                 src_factor: BlendFactor::One,
                 dst_factor: BlendFactor::One,
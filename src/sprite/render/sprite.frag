@@ -4,10 +4,11 @@ layout(location = 0) in vec2 v_Uv;
 
 layout(location = 0) out vec4 o_Target;
 
-// layout(set = 1, binding = 1) uniform SpriteInstance {
-//     vec4 Color;
-//     uint Flip;
-// };
+layout(set = 1, binding = 1) uniform SpriteInstance {
+    vec4 Color;
+    vec4 Dark;
+    uint Flip;
+};
 
 layout(set = 2, binding = 0) uniform Sprite_color_base {
     vec4 ColorBase;
@@ -19,11 +20,23 @@ layout(set = 2, binding = 2) uniform sampler Sprite_texture_sampler;
 #endif
 
 void main() {
-    // vec4 color = Color * ColorBase;
+    vec4 light = Color * ColorBase;
+    vec3 dark = Dark.rgb;
+#ifdef SPRITE_PMA
+    light.rgb *= light.a;
+    dark *= light.a;
+    float dark_alpha = 1.0;
+#else
+    float dark_alpha = 0.0;
+#endif
 
-    vec4 color = ColorBase;
 #ifdef SPRITE_TEXTURE
-    color *= texture(sampler2D(Sprite_texture, Sprite_texture_sampler), v_Uv);
+    vec4 texel = texture(sampler2D(Sprite_texture, Sprite_texture_sampler), v_Uv);
+#else
+    vec4 texel = vec4(1.0);
 #endif
-    o_Target = color;
+
+    // Spine tint black
+    o_Target.a = texel.a * light.a;
+    o_Target.rgb = ((texel.a - 1.0) * dark_alpha + 1.0 - texel.rgb) * dark + texel.rgb * light.rgb;
 }
@@ -14,6 +14,7 @@ layout(set = 1, binding = 0) uniform Transform {
 };
 // layout(set = 1, binding = 1) uniform SpriteInstance {
 //     vec4 Color;
+//     vec4 Dark;
 //     uint Flip;
 // };
 
@@ -11,8 +11,12 @@ use bevy::{
     },
     utils::HashSet,
 };
+use serde::{Deserialize, Serialize};
 
-use super::mesh_helper::MeshEditXU;
+use super::{
+    mesh_helper::MeshEditXU,
+    render::{is_sprite_pipeline, sprite_pipeline_handle},
+};
 
 #[derive(Debug)]
 pub enum Rotation {
@@ -72,6 +76,11 @@ pub struct Sprite {
     #[shader_def]
     #[reflect(ignore)]
     pub texture: Option<Handle<Texture>>,
+    /// Texture colors are premultiplied by their alpha, entities will use the premultiplied alpha
+    /// version of their [`SpriteBlend`] pipeline, see [`sprite_pipeline_handle`]
+    #[shader_def]
+    #[render_resources(ignore)]
+    pub pma: bool,
     #[reflect(ignore)]
     #[render_resources(ignore)]
     shape: SpriteShape,
@@ -87,6 +96,7 @@ impl Sprite {
             name: None,
             color_base: Default::default(),
             texture,
+            pma: false,
             shape,
             mesh: None,
         }
@@ -200,34 +210,76 @@ fn rebuild_mesh(sprite: &mut Sprite, meshes: &mut Assets<Mesh>) {
 
 ///////////////////////////////////////////////////////////////////////////////
 
-#[derive(Default, Debug, Clone, Reflect, RenderResources)]
+#[derive(Debug, Clone, Reflect, RenderResources)]
 #[render_resources(from_self)]
 #[reflect(Component)]
 pub struct SpriteInstance {
+    /// Tint multiplied with the sprite colors
     pub color: Color,
+    /// Tint added to the darker sprite colors (tint black), only the rgb channels are used
+    pub dark: Color,
     pub flip_x: bool,
     pub flip_y: bool,
 }
 
+/// How the sprite colors are blended with the colors behind it, each mode uses it's own pipeline
+#[derive(Serialize, Deserialize, Reflect, Clone, Copy, Debug, PartialEq)]
+#[reflect_value(Component, PartialEq, Serialize, Deserialize)]
+pub enum SpriteBlend {
+    Normal,
+    Additive,
+    Multiply,
+    Screen,
+}
+
+impl SpriteBlend {
+    pub const ALL: [SpriteBlend; 4] = [
+        SpriteBlend::Normal,
+        SpriteBlend::Additive,
+        SpriteBlend::Multiply,
+        SpriteBlend::Screen,
+    ];
+}
+
+impl Default for SpriteBlend {
+    fn default() -> Self {
+        SpriteBlend::Normal
+    }
+}
+
+impl Default for SpriteInstance {
+    fn default() -> Self {
+        Self {
+            color: Color::WHITE,
+            dark: Color::BLACK,
+            flip_x: false,
+            flip_y: false,
+        }
+    }
+}
+
 impl RenderResource for SpriteInstance {
     fn resource_type(&self) -> Option<RenderResourceType> {
         Some(RenderResourceType::Buffer)
     }
 
     fn buffer_byte_len(&self) -> Option<usize> {
-        Some(20)
+        // Padded to 16 bytes
+        Some(48)
     }
 
     fn write_buffer_bytes(&self, buffer: &mut [u8]) {
         // Write the size buffer
-        let (color_buffer, flip_buffer) = buffer.split_at_mut(16);
+        let (color_buffer, buffer) = buffer.split_at_mut(16);
         self.color.write_bytes(color_buffer);
+        let (dark_buffer, flip_buffer) = buffer.split_at_mut(16);
+        self.dark.write_bytes(dark_buffer);
 
         // First bit means flip x, second bit means flip y
         flip_buffer[0] = if self.flip_x { 0b01 } else { 0 } | if self.flip_y { 0b10 } else { 0 };
-        flip_buffer[1] = 0;
-        flip_buffer[2] = 0;
-        flip_buffer[3] = 0;
+        for byte in &mut flip_buffer[1..] {
+            *byte = 0;
+        }
     }
 
     fn texture(&self) -> Option<&Handle<Texture>> {
@@ -289,3 +341,24 @@ pub(crate) fn update_sprite_system(
         }
     }
 }
+
+pub(crate) fn update_sprite_pipeline_system(
+    sprites: Res<Assets<Sprite>>,
+    mut query: Query<
+        (&mut RenderPipelines, &Handle<Sprite>, &SpriteBlend),
+        Or<(Changed<Handle<Sprite>>, Changed<SpriteBlend>)>,
+    >,
+) {
+    for (mut render_pipelines, sprite_handle, blend) in query.iter_mut() {
+        if let Some(sprite) = sprites.get(sprite_handle) {
+            // Swap between the sprite pipelines, leaving any custom pipeline untouched
+            let pipeline_handle = sprite_pipeline_handle(*blend, sprite.pma);
+            for render_pipeline in render_pipelines.pipelines.iter_mut() {
+                let id = render_pipeline.pipeline.id;
+                if is_sprite_pipeline(id) && id != pipeline_handle.id {
+                    render_pipeline.pipeline = pipeline_handle.typed();
+                }
+            }
+        }
+    }
+}
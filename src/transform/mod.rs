@@ -36,6 +36,22 @@ pub enum Transform2D5System {
     ChildOfTransform2DPropagate,
 }
 
+/// Transform environment of 2D entities
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransformMode {
+    /// Pure 2D environment, uses [`Transform2D`] and [`LocalToWorld2D`], see [`Transform2DPlugin`]
+    Transform2D,
+    /// 2.5D environment (2D and 3D entities mixed together), uses [`Transform2D`] and [`LocalToWorld`],
+    /// see [`Transform2D5Plugin`]
+    Transform2D5,
+}
+
+impl Default for TransformMode {
+    fn default() -> Self {
+        TransformMode::Transform2D5
+    }
+}
+
 #[derive(Default)]
 pub struct Transform2DPlugin;
 